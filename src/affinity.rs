@@ -0,0 +1,163 @@
+//! CPU affinity of the workers
+//!
+//! Only supported on Linux. On other platforms, setting an
+//! [Affinity] makes [ThreadPool::new](crate::ThreadPool::new) fail.
+
+use std::fs;
+use std::path::Path;
+
+/// How the workers are pinned to the CPUs
+///
+/// The CPUs are ordered by NUMA node, read from `/sys/devices/system/node`.
+/// [Compact](Self::Compact) and [Scatter](Self::Scatter) only use the CPUs that
+/// the thread creating the pool can run on, so they respect it's cpuset.
+/// If there are more workers than CPUs, the CPUs are reused in order.
+#[derive(Clone,Debug,Default,PartialEq,Eq)]
+pub enum Affinity {
+    /// Don't pin the workers
+    #[default]
+    None,
+    /// Pin the `i`th worker to the `i`th core of the list
+    Cores(Vec<usize>),
+    /// Pin the `i`th worker to the `i`th set of CPUs of the list
+    CpuSets(Vec<Vec<usize>>),
+    /// Pin the workers to consecutive CPUs, filling a
+    /// NUMA node before moving to the next one
+    Compact,
+    /// Pin the workers to CPUs of alternating NUMA nodes
+    Scatter,
+}
+
+/// NUMA nodes of the system, each one with it's online CPUs
+/// that the current thread can run on
+fn topology() -> Vec<Vec<usize>> {
+    let mut online = read_cpulist("/sys/devices/system/cpu/online").unwrap_or_default();
+    if let Ok(allowed) = get_affinity() {
+        online.retain(|cpu| allowed.contains(cpu));
+    }
+    let mut nodes: Vec<(usize, Vec<usize>)> = fs::read_dir("/sys/devices/system/node")
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let id = name.to_str()?.strip_prefix("node")?.parse().ok()?;
+            let cpus = read_cpulist(entry.path().join("cpulist"))?;
+            Some((id, cpus))
+        })
+        .collect();
+    nodes.sort_unstable_by_key(|(id, _)| *id);
+
+    let mut nodes: Vec<Vec<usize>> = nodes
+        .into_iter()
+        .map(|(_, cpus)| cpus.into_iter().filter(|cpu| online.contains(cpu)).collect::<Vec<_>>())
+        .filter(|cpus| !cpus.is_empty())
+        .collect();
+    if nodes.is_empty() && !online.is_empty() {
+        nodes.push(online);
+    }
+    nodes
+}
+
+/// Parses a cpulist file, like "0-3,8-11"
+fn read_cpulist(path: impl AsRef<Path>) -> Option<Vec<usize>> {
+    parse_cpulist(&fs::read_to_string(path).ok()?)
+}
+
+fn parse_cpulist(list: &str) -> Option<Vec<usize>> {
+    let mut cpus = Vec::new();
+    for range in list.trim().split(',').filter(|r| !r.is_empty()) {
+        match range.split_once('-') {
+            Some((start, end)) => cpus.extend(start.parse::<usize>().ok()?..=end.parse().ok()?),
+            None => cpus.push(range.parse().ok()?),
+        }
+    }
+    Some(cpus)
+}
+
+/// The CPUs assigned to each worker, resolved from an [Affinity]
+#[derive(Debug)]
+pub(crate) struct Placement(Vec<Vec<usize>>);
+
+impl Placement {
+    pub fn new(affinity: &Affinity) -> Self {
+        let sets = match affinity {
+            Affinity::None => Vec::new(),
+            Affinity::Cores(cores) => cores.iter().map(|&c| vec![c]).collect(),
+            Affinity::CpuSets(sets) => sets.clone(),
+            Affinity::Compact => {
+                topology().into_iter().flatten().map(|c| vec![c]).collect()
+            }
+            Affinity::Scatter => {
+                let nodes = topology();
+                let max = nodes.iter().map(Vec::len).max().unwrap_or(0);
+                (0..max).flat_map(|i| nodes.iter().filter_map(move |n| n.get(i)))
+                        .map(|&c| vec![c])
+                        .collect()
+            }
+        };
+        Self(sets)
+    }
+
+    /// Returns the CPUs of the `index`th worker, if it should be pinned
+    pub fn cpus(&self, index: usize) -> Option<&[usize]> {
+        if self.0.is_empty() {
+            return None
+        }
+        Some(&self.0[index % self.0.len()])
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    use std::io;
+
+    /// Number of CPUs of a cpu_set_t
+    const SET_SIZE: usize = 1024;
+    const WORD: usize = u64::BITS as usize;
+
+    unsafe extern "C" {
+        fn sched_setaffinity(pid: i32, size: usize, mask: *const u64) -> i32;
+        fn sched_getaffinity(pid: i32, size: usize, mask: *mut u64) -> i32;
+    }
+
+    /// Pins the current thread to `cpus`
+    pub fn set_affinity(cpus: &[usize]) -> io::Result<()> {
+        let mut mask = [0_u64; SET_SIZE / WORD];
+        for &cpu in cpus {
+            if cpu >= SET_SIZE {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Invalid CPU: {cpu}")))
+            }
+            mask[cpu / WORD] |= 1 << (cpu % WORD);
+        }
+        /* SAFETY: mask is a valid buffer of the given size */
+        let res = unsafe { sched_setaffinity(0, size_of_val(&mask), mask.as_ptr()) };
+        if res == 0 { Ok(()) } else { Err(io::Error::last_os_error()) }
+    }
+
+    /// Returns the CPUs the current thread can run on
+    pub fn get_affinity() -> io::Result<Vec<usize>> {
+        let mut mask = [0_u64; SET_SIZE / WORD];
+        /* SAFETY: mask is a valid buffer of the given size */
+        let res = unsafe { sched_getaffinity(0, size_of_val(&mask), mask.as_mut_ptr()) };
+        if res != 0 {
+            return Err(io::Error::last_os_error())
+        }
+        Ok((0..SET_SIZE).filter(|cpu| mask[cpu / WORD] & (1 << (cpu % WORD)) != 0).collect())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod sys {
+    use std::io;
+
+    pub fn set_affinity(_cpus: &[usize]) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "CPU affinity is only supported on Linux"))
+    }
+
+    pub fn get_affinity() -> io::Result<Vec<usize>> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "CPU affinity is only supported on Linux"))
+    }
+}
+
+pub(crate) use sys::{get_affinity, set_affinity};
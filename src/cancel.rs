@@ -0,0 +1,125 @@
+use core::fmt;
+use core::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+
+/// Notified when a [CancellationToken] is cancelled
+pub(crate) trait OnCancel: Send + Sync {
+    fn cancel(&self);
+}
+
+struct Inner {
+    cancelled: AtomicBool,
+    parent: Option<CancellationToken>,
+    /// Queued jobs and child tokens to notify when cancelled
+    listeners: Mutex<Vec<Weak<dyn OnCancel>>>,
+}
+
+impl Inner {
+    fn new(parent: Option<CancellationToken>) -> Self {
+        Self {
+            cancelled: AtomicBool::new(false),
+            parent,
+            listeners: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl OnCancel for Inner {
+    /// Notifies the listeners of a child token
+    fn cancel(&self) {
+        let listeners = mem::take(&mut *self.listeners.lock().unwrap());
+        for listener in listeners.iter().filter_map(Weak::upgrade) {
+            listener.cancel();
+        }
+    }
+}
+
+/// A token to cancel jobs
+///
+/// Jobs sent with a token that has been cancelled are dropped
+/// without running. Queued jobs are dropped as soon as the token
+/// is cancelled, so they stop counting as pending right away.
+/// Jobs that are already running can check
+/// [is_cancelled](Self::is_cancelled) to stop early.
+///
+/// # Example
+/// ```
+/// use job_pool::{CancellationToken, ThreadPool};
+///
+/// let pool = ThreadPool::default();
+/// let token = CancellationToken::new();
+///
+/// let t = token.clone();
+/// pool.execute_with_token(token.clone(), move || {
+///     while !t.is_cancelled() {
+///         // do some work
+///         # break
+///     }
+/// });
+/// token.cancel();
+/// ```
+#[derive(Clone)]
+pub struct CancellationToken(Arc<Inner>);
+
+impl CancellationToken {
+    /// Creates a new token
+    pub fn new() -> Self {
+        Self(Arc::new(Inner::new(None)))
+    }
+
+    /// Creates a child token, which is cancelled when
+    /// either itself or `self` are cancelled.
+    pub fn child_token(&self) -> Self {
+        let child = Self(Arc::new(Inner::new(Some(self.clone()))));
+        let listener: Weak<dyn OnCancel> = Arc::downgrade(&child.0) as _;
+        self.register(listener);
+        child
+    }
+
+    /// Cancels this token
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::Release);
+        self.0.cancel();
+    }
+
+    /// Registers a listener, to be notified when this token is cancelled.
+    /// If it's already cancelled, the listener is notified right away.
+    pub(crate) fn register(&self, listener: Weak<dyn OnCancel>) {
+        let mut listeners = self.0.listeners.lock().unwrap();
+        /* Checked while holding the lock, so a concurrent cancel either
+         * sees the listener, or we see it's flag */
+        if self.is_cancelled() {
+            drop(listeners);
+            if let Some(listener) = listener.upgrade() {
+                listener.cancel();
+            }
+            return
+        }
+        /* Forget the listeners that are gone, before growing the list */
+        if listeners.len() == listeners.capacity() {
+            listeners.retain(|l| l.strong_count() > 0);
+        }
+        listeners.push(listener);
+    }
+
+    /// Returns true if this token, or any of it's parents, has been cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::Acquire)
+        || self.0.parent.as_ref().is_some_and(Self::is_cancelled)
+    }
+}
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancellationToken")
+         .field("cancelled", &self.is_cancelled())
+         .finish()
+    }
+}
@@ -0,0 +1,557 @@
+//! Work-stealing scheduler
+//!
+//! Each [ReceiverWrapper] gets it's own deque the first time it
+//! receives. Messages sent from a thread that owns a deque are pushed
+//! into it, and the rest go to a global injector. When a receiver runs
+//! out of messages in it's deque, it takes a batch from the injector,
+//! or steals from the other receivers.
+//!
+//! Every deque has a queue for each [Priority] level, and higher
+//! priority messages are always received first. If aging is enabled,
+//! a message that has been waiting for longer than the aging period
+//! (multiplied by it's level) is received before the higher ones.
+//!
+//! Control messages, sent with [SenderWrapper::send_control], skip
+//! the queues and are received before any other message.
+
+use std::cell::Cell;
+use std::collections::VecDeque;
+pub use std::sync::mpsc::{RecvError, RecvTimeoutError, SendError, TrySendError};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::Priority;
+
+/// Max number of messages taken from the injector at once
+const MAX_BATCH: usize = 16;
+
+thread_local! {
+    /// Deque owned by the current thread, as (scheduler, deque) pointers
+    static LOCAL: Cell<(*const (), *const ())> = const { Cell::new((std::ptr::null(), std::ptr::null())) };
+}
+
+pub fn sync_channel<T>(bound: usize, aging: Option<Duration>) -> (SenderWrapper<T>, ReceiverWrapper<T>) {
+    /* The scheduler has no rendezvous mode, so a bound
+     * of 0 holds one message, like a bound of 1 */
+    new(Some(bound.max(1)), aging)
+}
+
+pub fn channel<T>(aging: Option<Duration>) -> (SenderWrapper<T>, ReceiverWrapper<T>) {
+    new(None, aging)
+}
+
+fn new<T>(bound: Option<usize>, aging: Option<Duration>) -> (SenderWrapper<T>, ReceiverWrapper<T>) {
+    let shared = Arc::new(Shared {
+        injector: Mutex::new(Queue::new()),
+        locals: RwLock::new(Vec::new()),
+        control: Mutex::new(VecDeque::new()),
+        n_control: AtomicUsize::new(0),
+        queued: Default::default(),
+        injected: Default::default(),
+        len: AtomicUsize::new(0),
+        sleepers: AtomicUsize::new(0),
+        blocked_senders: AtomicUsize::new(0),
+        disconnected: AtomicBool::new(false),
+        closed: AtomicBool::new(false),
+        lock: Mutex::new(()),
+        not_empty: Condvar::new(),
+        not_full: Condvar::new(),
+        next_victim: AtomicUsize::new(0),
+        bound,
+        aging,
+    });
+    let s = SenderWrapper(Arc::clone(&shared));
+    let r = ReceiverWrapper { shared, local: OnceLock::new() };
+    (s,r)
+}
+
+struct Entry<T> {
+    msg: T,
+    sent: Instant,
+}
+
+/// A queue for each priority level
+struct Queue<T>([VecDeque<Entry<T>>; Priority::LEVELS]);
+
+impl<T> Queue<T> {
+    fn new() -> Self {
+        Self(Default::default())
+    }
+
+    fn push(&mut self, msg: T, priority: Priority) {
+        self.0[priority.index()].push_back(Entry { msg, sent: Instant::now() });
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.iter().all(VecDeque::is_empty)
+    }
+
+    /// Pops the oldest lower priority message that has been waiting
+    /// for more than `aging` times it's level. Returns it's level too.
+    fn pop_aged(&mut self, aging: Duration, now: Instant) -> Option<(usize, T)> {
+        (1..Priority::LEVELS).rev().find_map(|level| {
+            let front = self.0[level].front()?;
+            let waited = now.saturating_duration_since(front.sent);
+            if waited >= aging * level as u32 {
+                self.0[level].pop_front().map(|e| (level, e.msg))
+            } else {
+                None
+            }
+        })
+    }
+}
+
+type Local<T> = Mutex<Queue<T>>;
+
+/// A counter for each priority level
+type Counts = [AtomicUsize; Priority::LEVELS];
+
+struct Shared<T> {
+    injector: Mutex<Queue<T>>,
+    locals: RwLock<Vec<Arc<Local<T>>>>,
+    control: Mutex<VecDeque<T>>,
+    /// Number of control messages
+    n_control: AtomicUsize,
+    /// Number of queued messages of each level, across all the deques.
+    /// Updated while holding the lock of the deque.
+    queued: Counts,
+    /// Number of messages of each level in the injector
+    injected: Counts,
+    /// Number of queued messages, across all the deques
+    len: AtomicUsize,
+    /// Number of receivers waiting on `not_empty`
+    sleepers: AtomicUsize,
+    /// Number of senders waiting on `not_full`
+    blocked_senders: AtomicUsize,
+    disconnected: AtomicBool,
+    /// Set by [SenderWrapper::close]. Only control messages are sent after that.
+    closed: AtomicBool,
+    lock: Mutex<()>,
+    not_empty: Condvar,
+    not_full: Condvar,
+    next_victim: AtomicUsize,
+    bound: Option<usize>,
+    aging: Option<Duration>,
+}
+
+impl<T> Shared<T> {
+    fn id(self: &Arc<Self>) -> *const () {
+        Arc::as_ptr(self).cast()
+    }
+
+    /// Returns the deque of the current thread, if it belongs to this scheduler
+    fn current_local(self: &Arc<Self>) -> Option<&Local<T>> {
+        let (id, local) = LOCAL.get();
+        if id == self.id() {
+            /* SAFETY: LOCAL is only set by the ReceiverWrapper that owns
+             * this deque, and cleared when it drops. The ReceiverWrapper
+             * keeps the deque alive while it's set. */
+            Some(unsafe { &*local.cast::<Local<T>>() })
+        } else {
+            None
+        }
+    }
+
+    /// Reserves space for a message, blocking until there's space in the
+    /// queue, or until `deadline`. Returns false if the deadline passed
+    /// before that.
+    fn reserve(&self, deadline: Option<Instant>) -> bool {
+        let Some(bound) = self.bound else {
+            self.len.fetch_add(1, Ordering::SeqCst);
+            return true
+        };
+        let try_reserve = || {
+            self.len.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+                (n < bound).then_some(n + 1)
+            }).is_ok()
+        };
+        if try_reserve() {
+            return true
+        }
+        let mut guard = self.lock.lock().unwrap();
+        self.blocked_senders.fetch_add(1, Ordering::SeqCst);
+        let mut reserved = true;
+        while !try_reserve() {
+            match deadline {
+                Some(deadline) => {
+                    let timeout = deadline.saturating_duration_since(Instant::now());
+                    if timeout.is_zero() {
+                        reserved = false;
+                        break
+                    }
+                    guard = self.not_full.wait_timeout(guard, timeout).unwrap().0;
+                }
+                None => guard = self.not_full.wait(guard).unwrap(),
+            }
+        }
+        self.blocked_senders.fetch_sub(1, Ordering::SeqCst);
+        reserved
+    }
+
+    fn notify_pushed(&self) {
+        if self.sleepers.load(Ordering::SeqCst) > 0 {
+            let _guard = self.lock.lock().unwrap();
+            self.not_empty.notify_one();
+        }
+    }
+
+    fn notify_popped(&self) {
+        if self.bound.is_some() && self.blocked_senders.load(Ordering::SeqCst) > 0 {
+            let _guard = self.lock.lock().unwrap();
+            self.not_full.notify_one();
+        }
+    }
+
+    /// Pushes a message, for which space was already reserved
+    fn push(self: &Arc<Self>, t: T, priority: Priority) {
+        let level = priority.index();
+        match self.current_local() {
+            Some(local) => {
+                let mut local = local.lock().unwrap();
+                local.push(t, priority);
+                self.queued[level].fetch_add(1, Ordering::SeqCst);
+            }
+            None => {
+                let mut injector = self.injector.lock().unwrap();
+                injector.push(t, priority);
+                self.queued[level].fetch_add(1, Ordering::SeqCst);
+                self.injected[level].fetch_add(1, Ordering::SeqCst);
+            }
+        }
+        self.notify_pushed();
+    }
+
+    fn push_control(&self, t: T) {
+        self.control.lock().unwrap().push_back(t);
+        self.n_control.fetch_add(1, Ordering::SeqCst);
+        self.len.fetch_add(1, Ordering::SeqCst);
+        self.notify_pushed();
+    }
+
+    /// Levels that have queued messages, from highest to lowest
+    fn levels(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..Priority::LEVELS).filter(|&level| self.queued[level].load(Ordering::SeqCst) > 0)
+    }
+
+    fn pop(&self, local: &Local<T>) -> Option<T> {
+        let t = self.pop_control()
+            .or_else(|| self.pop_local(local))
+            .or_else(|| self.pop_aged_injector())
+            .or_else(|| {
+                self.levels().find_map(|level| {
+                    self.pop_local_level(local, level)
+                        .or_else(|| self.pop_injector(local, level))
+                        .or_else(|| self.steal(local, level))
+                })
+            })?;
+        self.len.fetch_sub(1, Ordering::SeqCst);
+        self.notify_popped();
+        Some(t)
+    }
+
+    fn pop_control(&self) -> Option<T> {
+        if self.n_control.load(Ordering::SeqCst) == 0 {
+            return None
+        }
+        let t = self.control.lock().unwrap().pop_front()?;
+        self.n_control.fetch_sub(1, Ordering::SeqCst);
+        Some(t)
+    }
+
+    /// Pops the highest priority message of the `local` deque, if there
+    /// are no higher priority messages queued in other deques. Messages
+    /// that have been waiting for longer than the aging period go first.
+    fn pop_local(&self, local: &Local<T>) -> Option<T> {
+        let top = self.levels().next()?;
+        let mut queue = local.lock().unwrap();
+        let aged = self.aging.and_then(|aging| queue.pop_aged(aging, Instant::now()));
+        let (level, t) = match aged {
+            Some(aged) => aged,
+            None => {
+                let level = queue.0.iter().position(|q| !q.is_empty())?;
+                if level > top {
+                    return None
+                }
+                (level, queue.0[level].pop_back()?.msg)
+            }
+        };
+        self.queued[level].fetch_sub(1, Ordering::SeqCst);
+        Some(t)
+    }
+
+    fn pop_local_level(&self, local: &Local<T>, level: usize) -> Option<T> {
+        let mut queue = local.lock().unwrap();
+        let t = queue.0[level].pop_back()?.msg;
+        self.queued[level].fetch_sub(1, Ordering::SeqCst);
+        Some(t)
+    }
+
+    /// Pops an aged message from the injector, if there may be any
+    fn pop_aged_injector(&self) -> Option<T> {
+        let aging = self.aging?;
+        if self.injected[1..].iter().all(|n| n.load(Ordering::SeqCst) == 0) {
+            return None
+        }
+        let mut injector = self.injector.lock().unwrap();
+        let (level, t) = injector.pop_aged(aging, Instant::now())?;
+        self.queued[level].fetch_sub(1, Ordering::SeqCst);
+        self.injected[level].fetch_sub(1, Ordering::SeqCst);
+        Some(t)
+    }
+
+    /// Takes a batch of messages from the injector, returning the
+    /// first one and moving the rest to the `local` deque.
+    fn pop_injector(&self, local: &Local<T>, level: usize) -> Option<T> {
+        if self.injected[level].load(Ordering::SeqCst) == 0 {
+            return None
+        }
+        let mut injector = self.injector.lock().unwrap();
+        let queue = &mut injector.0[level];
+        let first = queue.pop_front()?;
+        let n_locals = self.locals.read().unwrap().len().max(1);
+        let batch = (queue.len() / n_locals).min(MAX_BATCH);
+        if batch > 0 {
+            local.lock().unwrap().0[level].extend(queue.drain(..batch));
+        }
+        self.queued[level].fetch_sub(1, Ordering::SeqCst);
+        self.injected[level].fetch_sub(batch + 1, Ordering::SeqCst);
+        Some(first.msg)
+    }
+
+    fn steal(&self, local: &Local<T>, level: usize) -> Option<T> {
+        if self.queued[level].load(Ordering::SeqCst) <= self.injected[level].load(Ordering::SeqCst) {
+            return None
+        }
+        let locals = self.locals.read().unwrap();
+        let start = self.next_victim.fetch_add(1, Ordering::Relaxed);
+        (0..locals.len())
+            .map(|i| &locals[(start + i) % locals.len()])
+            .filter(|victim| !std::ptr::eq(&***victim, local))
+            .find_map(|victim| {
+                let mut victim = victim.lock().unwrap();
+                let t = victim.0[level].pop_front()?.msg;
+                self.queued[level].fetch_sub(1, Ordering::SeqCst);
+                Some(t)
+            })
+    }
+
+    /// Waits until there may be messages to receive.
+    /// Returns false if the deadline was reached.
+    fn sleep(&self, deadline: Option<Instant>) -> bool {
+        let mut guard = self.lock.lock().unwrap();
+        self.sleepers.fetch_add(1, Ordering::SeqCst);
+        let mut woken = true;
+        if self.len.load(Ordering::SeqCst) == 0 && !self.disconnected.load(Ordering::SeqCst) {
+            match deadline {
+                Some(deadline) => {
+                    let timeout = deadline.saturating_duration_since(Instant::now());
+                    let (_guard, res) = self.not_empty.wait_timeout(guard, timeout).unwrap();
+                    woken = !res.timed_out();
+                    guard = _guard;
+                }
+                None => guard = self.not_empty.wait(guard).unwrap(),
+            }
+        }
+        self.sleepers.fetch_sub(1, Ordering::SeqCst);
+        drop(guard);
+        woken
+    }
+}
+
+pub struct SenderWrapper<T>(Arc<Shared<T>>);
+
+impl<T> SenderWrapper<T> {
+    /// Sends a message, blocking while the queue is full
+    ///
+    /// # Errors
+    /// If the channel was [closed](Self::close)
+    pub fn send_with_priority(&self, t: T, priority: Priority) -> std::result::Result<(),SendError<T>> {
+        match self.reserve(None) {
+            Ok(permit) => {
+                permit.send(t, priority);
+                Ok(())
+            }
+            Err(_) => Err(SendError(t)),
+        }
+    }
+
+    /// Sends a message without blocking, even if the queue is full
+    ///
+    /// # Errors
+    /// If the channel was [closed](Self::close)
+    pub fn force_send_with_priority(&self, t: T, priority: Priority) -> std::result::Result<(),SendError<T>> {
+        if self.0.closed.load(Ordering::Acquire) {
+            return Err(SendError(t))
+        }
+        self.0.len.fetch_add(1, Ordering::SeqCst);
+        self.0.push(t, priority);
+        Ok(())
+    }
+
+    /// Reserves space for a message, waiting until `deadline` at most
+    /// while the queue is full. Waits forever if it's None.
+    ///
+    /// # Errors
+    /// - [TrySendError::Full] if there was no space by `deadline`
+    /// - [TrySendError::Disconnected] if the channel was [closed](Self::close)
+    pub fn reserve(&self, deadline: Option<Instant>) -> std::result::Result<Permit<'_, T>,TrySendError<()>> {
+        if self.0.closed.load(Ordering::Acquire) {
+            return Err(TrySendError::Disconnected(()))
+        }
+        if !self.0.reserve(deadline) {
+            return Err(TrySendError::Full(()))
+        }
+        Ok(Permit(Some(&self.0)))
+    }
+
+    /// Closes the channel. Messages sent after this are given back,
+    /// except for [control](Self::send_control) messages.
+    ///
+    /// The receivers still get the messages that were already queued,
+    /// and a disconnected error once there are none left.
+    pub fn close(&self) {
+        self.0.closed.store(true, Ordering::Release);
+        let _guard = self.0.lock.lock().unwrap();
+        self.0.disconnected.store(true, Ordering::SeqCst);
+        self.0.not_empty.notify_all();
+    }
+
+    /// Sends a message that is received before any other
+    pub fn send_control(&self, t: T) {
+        self.0.push_control(t);
+    }
+
+    /// Returns the number of queued messages
+    pub fn len(&self) -> usize {
+        self.0.len.load(Ordering::SeqCst)
+    }
+
+    /// Returns the number of queued messages, without counting
+    /// the [control](Self::send_control) messages or the space
+    /// reserved by [Permit]s that haven't been sent yet
+    pub fn queued(&self) -> usize {
+        self.0.queued.iter().map(|n| n.load(Ordering::SeqCst)).sum()
+    }
+}
+
+impl<T> Drop for SenderWrapper<T> {
+    fn drop(&mut self) {
+        let _guard = self.0.lock.lock().unwrap();
+        self.0.disconnected.store(true, Ordering::SeqCst);
+        self.0.not_empty.notify_all();
+    }
+}
+
+/// Space for a message, reserved with [SenderWrapper::reserve].
+/// If it's dropped without sending, the space is released.
+pub struct Permit<'a, T>(Option<&'a Arc<Shared<T>>>);
+
+impl<T> Permit<'_, T> {
+    pub fn send(mut self, t: T, priority: Priority) {
+        if let Some(shared) = self.0.take() {
+            shared.push(t, priority);
+        }
+    }
+}
+
+impl<T> Drop for Permit<'_, T> {
+    fn drop(&mut self) {
+        if let Some(shared) = self.0.take() {
+            shared.len.fetch_sub(1, Ordering::SeqCst);
+            shared.notify_popped();
+        }
+    }
+}
+
+pub struct ReceiverWrapper<T> {
+    shared: Arc<Shared<T>>,
+    /// This receiver's deque, registered on the first receive
+    local: OnceLock<Arc<Local<T>>>,
+}
+
+impl<T> Clone for ReceiverWrapper<T> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: Arc::clone(&self.shared),
+            local: OnceLock::new(),
+        }
+    }
+}
+
+impl<T> ReceiverWrapper<T> {
+    fn local(&self) -> &Local<T> {
+        self.local.get_or_init(|| {
+            let local = Arc::new(Mutex::new(Queue::new()));
+            self.shared.locals.write().unwrap().push(Arc::clone(&local));
+            LOCAL.set((self.shared.id(), Arc::as_ptr(&local).cast()));
+            local
+        })
+    }
+
+    pub fn recv(&self) -> Result<T, RecvError> {
+        self.recv_deadline(None).map_err(|_| RecvError)
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        self.recv_deadline(Some(Instant::now() + timeout))
+    }
+
+    /// Returns true if there are no queued messages
+    pub fn is_empty(&self) -> bool {
+        self.shared.len.load(Ordering::SeqCst) == 0
+    }
+
+    /// Returns true if the current thread owns a deque of this scheduler
+    pub fn is_local_thread(&self) -> bool {
+        self.shared.current_local().is_some()
+    }
+
+    /// Receives a message without blocking, using the deque of the
+    /// current thread. Returns None if the thread doesn't own a
+    /// deque, or there are no messages.
+    pub fn try_recv_local(&self) -> Option<T> {
+        let local = self.shared.current_local()?;
+        self.shared.pop(local)
+    }
+
+    fn recv_deadline(&self, deadline: Option<Instant>) -> Result<T, RecvTimeoutError> {
+        let local = self.local();
+        loop {
+            if let Some(t) = self.shared.pop(local) {
+                return Ok(t)
+            }
+            if self.shared.disconnected.load(Ordering::SeqCst)
+                && self.shared.len.load(Ordering::SeqCst) == 0
+            {
+                return Err(RecvTimeoutError::Disconnected)
+            }
+            if !self.shared.sleep(deadline) {
+                return self.shared.pop(local).ok_or(RecvTimeoutError::Timeout)
+            }
+        }
+    }
+}
+
+impl<T> Drop for ReceiverWrapper<T> {
+    fn drop(&mut self) {
+        let Some(local) = self.local.take() else { return };
+        if LOCAL.get().1 == Arc::as_ptr(&local).cast() {
+            LOCAL.set((std::ptr::null(), std::ptr::null()));
+        }
+        self.shared.locals.write().unwrap().retain(|l| !Arc::ptr_eq(l, &local));
+
+        /* Give the remaining messages to the other receivers */
+        let remaining = std::mem::replace(&mut *local.lock().unwrap(), Queue::new());
+        if !remaining.is_empty() {
+            let mut injector = self.shared.injector.lock().unwrap();
+            for (level, (queue, remaining)) in injector.0.iter_mut().zip(remaining.0).enumerate() {
+                self.shared.injected[level].fetch_add(remaining.len(), Ordering::SeqCst);
+                queue.extend(remaining);
+            }
+            drop(injector);
+            let _guard = self.shared.lock.lock().unwrap();
+            self.shared.not_empty.notify_all();
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use core::time::Duration;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use crate::handle::job_result;
+use crate::{PoolError, Result, ThreadPool};
+
+/// Sends the result of a job to a [CompletionQueue]
+///
+/// If the job is dropped without running, it sends a [PoolError::ShutDown]
+/// instead, so the queue never waits for a result that won't come.
+struct ResultSender<T>(Option<Sender<Result<T>>>);
+
+impl<T> ResultSender<T> {
+    fn send(mut self, result: Result<T>) {
+        if let Some(sender) = self.0.take() {
+            let _ = sender.send(result);
+        }
+    }
+}
+
+impl<T> Drop for ResultSender<T> {
+    fn drop(&mut self) {
+        if let Some(sender) = self.0.take() {
+            let _ = sender.send(Err(PoolError::ShutDown));
+        }
+    }
+}
+
+/// Submits jobs to a [ThreadPool], and returns their results
+/// in the order they finish.
+///
+/// # Example
+/// ```
+/// use job_pool::{CompletionQueue, ThreadPool};
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// let pool = ThreadPool::default();
+/// let mut queue = CompletionQueue::new(&pool);
+/// queue.submit(|| { thread::sleep(Duration::from_millis(100)); "slow" });
+/// queue.submit(|| "fast");
+///
+/// assert_eq!(queue.next().unwrap().unwrap(), "fast");
+/// assert_eq!(queue.next().unwrap().unwrap(), "slow");
+/// assert!(queue.next().is_none());
+/// ```
+pub struct CompletionQueue<'pool, T> {
+    pool: &'pool ThreadPool,
+    pending: usize,
+    sender: Sender<Result<T>>,
+    receiver: Receiver<Result<T>>,
+}
+
+impl<'pool, T: Send + 'static> CompletionQueue<'pool, T> {
+    /// Creates a new [CompletionQueue] that submits it's jobs to `pool`
+    pub fn new(pool: &'pool ThreadPool) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self { pool, pending: 0, sender, receiver }
+    }
+
+    /// Executes `f` in the pool. It's result can be taken with
+    /// [next](Self::next) once it finishes.
+    pub fn submit<F>(&mut self, f: F)
+    where
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let sender = ResultSender(Some(self.sender.clone()));
+        self.pool.execute(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(f));
+            sender.send(job_result(result));
+        });
+        self.pending += 1;
+    }
+
+    /// Returns the number of submitted jobs whose result
+    /// hasn't been taken yet
+    pub fn pending(&self) -> usize {
+        self.pending
+    }
+
+    /// Returns the result of a finished job, if there's any
+    pub fn try_next(&mut self) -> Option<Result<T>> {
+        self.next_timeout(Duration::ZERO)
+    }
+
+    /// Waits for the next job to finish, for at most `timeout`.
+    ///
+    /// Returns None if there are no pending jobs, or
+    /// none of them finished in time.
+    pub fn next_timeout(&mut self, timeout: Duration) -> Option<Result<T>> {
+        if self.pending == 0 {
+            return None
+        }
+        let result = self.receiver.recv_timeout(timeout).ok()?;
+        self.pending -= 1;
+        Some(result)
+    }
+}
+
+/// Waits for the jobs to finish, and yields their results.
+///
+/// Ends once there are no pending jobs, but more jobs can be
+/// submitted after that.
+impl<T: Send + 'static> Iterator for CompletionQueue<'_, T> {
+    type Item = Result<T>;
+
+    /// Waits for the next job to finish, and returns it's result.
+    ///
+    /// Returns None if there are no pending jobs. If a job was dropped
+    /// without running, it's result is a [PoolError::ShutDown].
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pending == 0 {
+            return None
+        }
+        /* Every pending job sends exactly one result, even if it
+         * never runs, so this doesn't wait forever */
+        let result = self.receiver.recv().ok()?;
+        self.pending -= 1;
+        Some(result)
+    }
+}
@@ -1,36 +1,263 @@
-use crate::Result;
+use core::fmt;
+use core::mem;
+use core::time::Duration;
+use std::sync::Arc;
+
+use crate::parallelism::{self, DEFAULT_WORKERS};
+use crate::{Affinity, JobPanic, ParallelismSource, PoolError, Result, SchedPolicy};
+
+/// Function called when a job panics, under [PanicPolicy::Handler]
+pub type PanicHandler = Arc<dyn Fn(JobPanic) + Send + Sync>;
+
+/// What to do when a job panics
+///
+/// The panic message is printed by the panic hook, like for any
+/// other thread. Unless the policy is [Abort](Self::Abort), the
+/// worker that ran the job keeps running, and the job is counted
+/// as finished.
+///
+/// This doesn't apply to jobs with a handle (like the ones created
+/// by [ThreadPool::spawn](crate::ThreadPool::spawn)), since their
+/// panic is returned when joining the handle.
+#[derive(Clone,Default)]
+pub enum PanicPolicy {
+    /// Continue, leaving the report to the panic hook
+    #[default]
+    Log,
+    /// Forward the panic payload to the given handler.
+    /// If the handler panics, the worker still keeps running.
+    Handler(PanicHandler),
+    /// Abort the process
+    Abort,
+}
+
+impl fmt::Debug for PanicPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Log => write!(f, "Log"),
+            Self::Handler(_) => write!(f, "Handler(..)"),
+            Self::Abort => write!(f, "Abort"),
+        }
+    }
+}
+
+/// Function called by the workers, under [Hooks]
+pub type Hook = Arc<dyn Fn() + Send + Sync>;
+
+/// Function called after each job, with it's run time and
+/// whether it panicked. See [Hooks::after_job]
+pub type AfterJobHook = Arc<dyn Fn(Duration, bool) + Send + Sync>;
+
+/// Functions called by the workers at points of their lifecycle
+///
+/// They run in the worker's thread, so they can set up thread locals.
+/// The job hooks aren't called for jobs that were cancelled.
+/// If a hook panics, the worker keeps running.
+#[derive(Clone,Default)]
+pub struct Hooks {
+    /// Called when a worker thread starts, before it runs any job
+    pub on_thread_start: Option<Hook>,
+    /// Called when a worker thread is about to exit
+    pub on_thread_stop: Option<Hook>,
+    /// Called before running each job, or polling a future
+    pub before_job: Option<Hook>,
+    /// Called after running each job, or polling a future
+    pub after_job: Option<AfterJobHook>,
+}
+
+impl Hooks {
+    const fn new() -> Self {
+        Self {
+            on_thread_start: None,
+            on_thread_stop: None,
+            before_job: None,
+            after_job: None,
+        }
+    }
+}
+
+impl fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hook = |set: bool| if set { "Some(..)" } else { "None" };
+        f.debug_struct("Hooks")
+         .field("on_thread_start", &hook(self.on_thread_start.is_some()))
+         .field("on_thread_stop", &hook(self.on_thread_stop.is_some()))
+         .field("before_job", &hook(self.before_job.is_some()))
+         .field("after_job", &hook(self.after_job.is_some()))
+         .finish()
+    }
+}
+
+/// Name of the worker threads
+#[derive(Clone)]
+pub enum ThreadName {
+    /// Name the workers "{prefix}-{index}"
+    Prefix(String),
+    /// Build the name from the index of the worker
+    Fn(Arc<dyn Fn(usize) -> String + Send + Sync>),
+}
+
+impl ThreadName {
+    pub(crate) fn name(&self, index: usize) -> String {
+        match self {
+            Self::Prefix(prefix) => format!("{prefix}-{index}"),
+            Self::Fn(f) => f(index),
+        }
+    }
+}
+
+impl fmt::Debug for ThreadName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Prefix(prefix) => f.debug_tuple("Prefix").field(prefix).finish(),
+            Self::Fn(_) => write!(f, "Fn(..)"),
+        }
+    }
+}
 
 /// Pool Config
 ///
 /// Configuration for the [ThreadPool](crate::ThreadPool)
-#[derive(Clone,Copy,Debug)]
+#[derive(Clone,Debug)]
 pub struct PoolConfig {
     pub n_workers: u16,
     pub max_jobs: Option<u16>,
+    /// Maximun number of jobs waiting in the queue. When it's full,
+    /// sending a job blocks until a worker takes one.
+    ///
+    /// A size of 0 is treated as 1: the queue holds one job, instead
+    /// of handing each job directly to a worker.
     pub incoming_buf_size: Option<u16>,
+    pub panic_policy: PanicPolicy,
+    /// Minimun number of workers. Workers above this number exit
+    /// after being idle for [keep_alive](Self::keep_alive).
+    ///
+    /// Defaults to [n_workers](Self::n_workers)
+    pub min_workers: Option<u16>,
+    /// Maximun number of workers. When a job is sent and all the
+    /// workers are busy, a new worker is spawned, up to this number.
+    ///
+    /// Defaults to [n_workers](Self::n_workers)
+    pub max_workers: Option<u16>,
+    /// Time an idle worker waits for a job before exiting, if
+    /// there are more than [min_workers](Self::min_workers)
+    pub keep_alive: Duration,
+    /// If set, a job waiting in the queue for longer than this
+    /// period times it's [priority](crate::Priority) level is run
+    /// before the higher priority jobs.
+    pub aging: Option<Duration>,
+    pub hooks: Hooks,
+    /// Name of the worker threads. By default, they are unnamed.
+    pub thread_name: Option<ThreadName>,
+    /// Stack size of the worker threads, in bytes. By
+    /// default, the one of [std::thread] is used.
+    pub stack_size: Option<usize>,
+    /// CPUs the workers are pinned to. Only supported on Linux.
+    pub affinity: Affinity,
+    /// Scheduling policy of the workers. Only supported on Linux.
+    pub sched_policy: Option<SchedPolicy>,
+    /// Nice value of the workers, between -20 and 19.
+    /// Only supported on Linux.
+    pub nice: Option<i8>,
 }
 
 impl PoolConfig {
+    /// Returns the default configuration, with one worker
+    /// for each CPU available to the process.
+    ///
+    /// See [detect_workers](Self::detect_workers)
+    pub fn auto() -> Self {
+        PoolConfig::builder()
+                   .n_workers(Self::detect_workers().0)
+                   .build()
+    }
+
+    /// Returns the number of CPUs available to the process, and the
+    /// [source](ParallelismSource) that decided it.
+    ///
+    /// It takes into account the cgroup CPU quota (v1 and v2) and
+    /// the CPU affinity mask of the process, on top of
+    /// [std::thread::available_parallelism]. If more than one of
+    /// them applies, the lowest one is used.
+    ///
+    /// # Example
+    /// ```
+    /// use job_pool::PoolConfig;
+    ///
+    /// let (n, source) = PoolConfig::detect_workers();
+    /// println!("Using {n} workers, based on {source:?}");
+    /// ```
+    pub fn detect_workers() -> (u16, ParallelismSource) {
+        parallelism::detect()
+    }
+
     pub const fn builder() -> PoolConfigBuilder {
         PoolConfigBuilder {
-            n_workers: 16,
+            n_workers: DEFAULT_WORKERS,
             max_jobs: None,
             incoming_buf_size: None,
+            panic_policy: PanicPolicy::Log,
+            min_workers: None,
+            max_workers: None,
+            keep_alive: Duration::from_secs(60),
+            aging: None,
+            hooks: Hooks::new(),
+            thread_name: None,
+            stack_size: None,
+            affinity: Affinity::None,
+            sched_policy: None,
+            nice: None,
         }
     }
 
+    #[allow(clippy::collapsible_if)]
     pub fn validate(&self) -> Result<()> {
-        if self.n_workers == 0 {
-            return Err("Invalid pool size: 0".into());
+        validate_workers(self.n_workers, self.max_jobs)?;
+        if let Some(min) = self.min_workers {
+            if min > self.n_workers {
+                return Err(PoolError::MinWorkersTooHigh { min_workers: min, n_workers: self.n_workers })
+            }
         }
-        if let Some(max) = self.max_jobs {
+        if let Some(max) = self.max_workers {
             if max < self.n_workers {
-                return Err(format!("Max number of jobs ({max}) is lower \
-                        than the number of workers ({})", self.n_workers).into())
+                return Err(PoolError::MaxWorkersTooLow { max_workers: max, n_workers: self.n_workers })
+            }
+        }
+        if let Some(nice) = self.nice {
+            if !(-20..=19).contains(&nice) {
+                return Err(PoolError::InvalidNice(nice))
+            }
+        }
+        if let Some(SchedPolicy::Fifo(p) | SchedPolicy::RoundRobin(p)) = self.sched_policy {
+            if !(1..=99).contains(&p) {
+                return Err(PoolError::InvalidRealTimePriority(p))
             }
         }
         Ok(())
     }
+
+    /// Returns the minimun number of workers
+    pub fn min_workers(&self) -> u16 {
+        self.min_workers.unwrap_or(self.n_workers)
+    }
+
+    /// Returns the maximun number of workers
+    pub fn max_workers(&self) -> u16 {
+        self.max_workers.unwrap_or(self.n_workers)
+    }
+}
+
+#[allow(clippy::collapsible_if)]
+pub(crate) fn validate_workers(n_workers: u16, max_jobs: Option<u16>) -> Result<()> {
+    if n_workers == 0 {
+        return Err(PoolError::InvalidPoolSize);
+    }
+    if let Some(max) = max_jobs {
+        if max < n_workers {
+            return Err(PoolError::MaxJobsTooLow { max_jobs: max, n_workers })
+        }
+    }
+    Ok(())
 }
 
 impl Default for PoolConfig {
@@ -39,6 +266,17 @@ impl Default for PoolConfig {
     /// Nº Workers: 16
     /// Max Jobs: None
     /// Incoming buf size: None
+    /// Panic policy: [Log](PanicPolicy::Log)
+    /// Min workers: Nº Workers
+    /// Max workers: Nº Workers
+    /// Keep alive: 60 seconds
+    /// Aging: None
+    /// Hooks: None
+    /// Thread name: None
+    /// Stack size: None
+    /// Affinity: [None](Affinity::None)
+    /// Scheduling policy: None
+    /// Nice: None
     fn default() -> Self {
         PoolConfig::builder().build()
     }
@@ -48,6 +286,17 @@ pub struct PoolConfigBuilder {
     n_workers: u16,
     max_jobs: Option<u16>,
     incoming_buf_size: Option<u16>,
+    panic_policy: PanicPolicy,
+    min_workers: Option<u16>,
+    max_workers: Option<u16>,
+    keep_alive: Duration,
+    aging: Option<Duration>,
+    hooks: Hooks,
+    thread_name: Option<ThreadName>,
+    stack_size: Option<usize>,
+    affinity: Affinity,
+    sched_policy: Option<SchedPolicy>,
+    nice: Option<i8>,
 }
 
 impl PoolConfigBuilder {
@@ -75,11 +324,147 @@ impl PoolConfigBuilder {
         self.incoming_buf_size = Some(n);
         self
     }
-    pub const fn build(self) -> PoolConfig {
-        PoolConfig {
+    pub const fn aging(mut self, aging: Duration) -> Self {
+        self.aging = Some(aging);
+        self
+    }
+    pub const fn set_aging(&mut self, aging: Duration) -> &mut Self {
+        self.aging = Some(aging);
+        self
+    }
+    pub fn panic_policy(mut self, policy: PanicPolicy) -> Self {
+        self.panic_policy = policy;
+        self
+    }
+    pub fn set_panic_policy(&mut self, policy: PanicPolicy) -> &mut Self {
+        self.panic_policy = policy;
+        self
+    }
+    pub const fn min_workers(mut self, n: u16) -> Self {
+        self.min_workers = Some(n);
+        self
+    }
+    pub const fn set_min_workers(&mut self, n: u16) -> &mut Self {
+        self.min_workers = Some(n);
+        self
+    }
+    pub const fn max_workers(mut self, n: u16) -> Self {
+        self.max_workers = Some(n);
+        self
+    }
+    pub const fn set_max_workers(&mut self, n: u16) -> &mut Self {
+        self.max_workers = Some(n);
+        self
+    }
+    pub const fn keep_alive(mut self, keep_alive: Duration) -> Self {
+        self.keep_alive = keep_alive;
+        self
+    }
+    pub const fn set_keep_alive(&mut self, keep_alive: Duration) -> &mut Self {
+        self.keep_alive = keep_alive;
+        self
+    }
+    pub fn on_thread_start(mut self, f: impl Fn() + Send + Sync + 'static) -> Self {
+        self.hooks.on_thread_start = Some(Arc::new(f));
+        self
+    }
+    pub fn set_on_thread_start(&mut self, f: impl Fn() + Send + Sync + 'static) -> &mut Self {
+        self.hooks.on_thread_start = Some(Arc::new(f));
+        self
+    }
+    pub fn on_thread_stop(mut self, f: impl Fn() + Send + Sync + 'static) -> Self {
+        self.hooks.on_thread_stop = Some(Arc::new(f));
+        self
+    }
+    pub fn set_on_thread_stop(&mut self, f: impl Fn() + Send + Sync + 'static) -> &mut Self {
+        self.hooks.on_thread_stop = Some(Arc::new(f));
+        self
+    }
+    pub fn before_job(mut self, f: impl Fn() + Send + Sync + 'static) -> Self {
+        self.hooks.before_job = Some(Arc::new(f));
+        self
+    }
+    pub fn set_before_job(&mut self, f: impl Fn() + Send + Sync + 'static) -> &mut Self {
+        self.hooks.before_job = Some(Arc::new(f));
+        self
+    }
+    pub fn after_job(mut self, f: impl Fn(Duration, bool) + Send + Sync + 'static) -> Self {
+        self.hooks.after_job = Some(Arc::new(f));
+        self
+    }
+    pub fn set_after_job(&mut self, f: impl Fn(Duration, bool) + Send + Sync + 'static) -> &mut Self {
+        self.hooks.after_job = Some(Arc::new(f));
+        self
+    }
+    pub fn thread_name_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.thread_name = Some(ThreadName::Prefix(prefix.into()));
+        self
+    }
+    pub fn set_thread_name_prefix(&mut self, prefix: impl Into<String>) -> &mut Self {
+        self.thread_name = Some(ThreadName::Prefix(prefix.into()));
+        self
+    }
+    pub fn thread_name_fn(mut self, f: impl Fn(usize) -> String + Send + Sync + 'static) -> Self {
+        self.thread_name = Some(ThreadName::Fn(Arc::new(f)));
+        self
+    }
+    pub fn set_thread_name_fn(&mut self, f: impl Fn(usize) -> String + Send + Sync + 'static) -> &mut Self {
+        self.thread_name = Some(ThreadName::Fn(Arc::new(f)));
+        self
+    }
+    pub const fn stack_size(mut self, size: usize) -> Self {
+        self.stack_size = Some(size);
+        self
+    }
+    pub const fn set_stack_size(&mut self, size: usize) -> &mut Self {
+        self.stack_size = Some(size);
+        self
+    }
+    pub fn affinity(mut self, affinity: Affinity) -> Self {
+        self.affinity = affinity;
+        self
+    }
+    pub fn set_affinity(&mut self, affinity: Affinity) -> &mut Self {
+        self.affinity = affinity;
+        self
+    }
+    pub const fn sched_policy(mut self, policy: SchedPolicy) -> Self {
+        self.sched_policy = Some(policy);
+        self
+    }
+    pub const fn set_sched_policy(&mut self, policy: SchedPolicy) -> &mut Self {
+        self.sched_policy = Some(policy);
+        self
+    }
+    pub const fn nice(mut self, nice: i8) -> Self {
+        self.nice = Some(nice);
+        self
+    }
+    pub const fn set_nice(&mut self, nice: i8) -> &mut Self {
+        self.nice = Some(nice);
+        self
+    }
+    pub const fn build(mut self) -> PoolConfig {
+        /* The builder can't be dropped in a const fn. So the fields
+         * that own memory are swapped with empty values, and the
+         * builder is forgotten, which doesn't leak anything. */
+        let config = PoolConfig {
             n_workers: self.n_workers,
             incoming_buf_size: self.incoming_buf_size,
             max_jobs: self.max_jobs,
-        }
+            panic_policy: mem::replace(&mut self.panic_policy, PanicPolicy::Log),
+            min_workers: self.min_workers,
+            max_workers: self.max_workers,
+            keep_alive: self.keep_alive,
+            aging: self.aging,
+            hooks: mem::replace(&mut self.hooks, Hooks::new()),
+            thread_name: self.thread_name.take(),
+            stack_size: self.stack_size,
+            affinity: mem::replace(&mut self.affinity, Affinity::None),
+            sched_policy: self.sched_policy,
+            nice: self.nice,
+        };
+        mem::forget(self);
+        config
     }
 }
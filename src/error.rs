@@ -0,0 +1,174 @@
+use core::fmt;
+use std::io;
+
+use crate::{JobPanic, ScopeError};
+
+/// A setting applied by each worker when it starts
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum ThreadSetting {
+    /// The [Affinity](crate::Affinity) of the worker
+    Affinity,
+    /// The [SchedPolicy](crate::SchedPolicy) of the worker
+    SchedPolicy,
+    /// The [nice](crate::PoolConfig::nice) value of the worker
+    Niceness,
+}
+
+impl fmt::Display for ThreadSetting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Affinity => write!(f, "CPU affinity"),
+            Self::SchedPolicy => write!(f, "scheduling policy"),
+            Self::Niceness => write!(f, "nice value"),
+        }
+    }
+}
+
+/// Error returned by the [ThreadPool](crate::ThreadPool)
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum PoolError {
+    /// The number of workers is 0
+    InvalidPoolSize,
+    /// [max_jobs](crate::PoolConfig::max_jobs) is lower than the number of workers
+    MaxJobsTooLow { max_jobs: u16, n_workers: u16 },
+    /// [min_workers](crate::PoolConfig::min_workers) is greater than the number of workers
+    MinWorkersTooHigh { min_workers: u16, n_workers: u16 },
+    /// [max_workers](crate::PoolConfig::max_workers) is lower than the number of workers
+    MaxWorkersTooLow { max_workers: u16, n_workers: u16 },
+    /// The [nice](crate::PoolConfig::nice) value is not between -20 and 19
+    InvalidNice(i8),
+    /// The priority of a real time [SchedPolicy](crate::SchedPolicy) is not between 1 and 99
+    InvalidRealTimePriority(u8),
+    /// The period of a [periodic job](crate::ThreadPool::execute_every) is zero
+    InvalidPeriod,
+    /// A worker thread couldn't be spawned
+    Spawn(io::Error),
+    /// A worker thread couldn't apply one of it's settings.
+    ///
+    /// For example, raising the priority of the workers
+    /// requires the CAP_SYS_NICE capability.
+    ThreadSetup {
+        setting: ThreadSetting,
+        source: io::Error,
+    },
+    /// The pool has no workers left to run the jobs
+    ShutDown,
+    /// The pool can't take more jobs right now
+    QueueFull,
+    /// The operation didn't finish in time
+    TimedOut,
+    /// A job panicked
+    JobPanicked(JobPanic),
+    /// A job was dropped without running, because it's
+    /// [CancellationToken](crate::CancellationToken) was cancelled
+    Cancelled,
+    /// One or more jobs of a [scope](crate::ThreadPool::try_scope) panicked
+    ScopePanicked(ScopeError),
+}
+
+impl fmt::Display for PoolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidPoolSize => write!(f, "Invalid pool size: 0"),
+            Self::MaxJobsTooLow { max_jobs, n_workers } => {
+                write!(f, "Max number of jobs ({max_jobs}) is lower \
+                           than the number of workers ({n_workers})")
+            }
+            Self::MinWorkersTooHigh { min_workers, n_workers } => {
+                write!(f, "Min number of workers ({min_workers}) is greater \
+                           than the number of workers ({n_workers})")
+            }
+            Self::MaxWorkersTooLow { max_workers, n_workers } => {
+                write!(f, "Max number of workers ({max_workers}) is lower \
+                           than the number of workers ({n_workers})")
+            }
+            Self::InvalidNice(nice) => write!(f, "Invalid nice value: {nice}"),
+            Self::InvalidRealTimePriority(p) => write!(f, "Invalid real time priority: {p}"),
+            Self::InvalidPeriod => write!(f, "Invalid period: 0"),
+            Self::Spawn(err) => write!(f, "Error spawning worker thread: {err}"),
+            Self::ThreadSetup { setting, source } => {
+                write!(f, "Error setting the {setting} of a worker thread: {source}")
+            }
+            Self::ShutDown => write!(f, "The pool has shut down"),
+            Self::QueueFull => write!(f, "The pool's queue is full"),
+            Self::TimedOut => write!(f, "Timed out"),
+            Self::JobPanicked(panic) => write!(f, "{panic}"),
+            Self::Cancelled => write!(f, "The job was cancelled"),
+            Self::ScopePanicked(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for PoolError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Spawn(err) | Self::ThreadSetup { source: err, .. } => Some(err),
+            Self::JobPanicked(panic) => Some(panic),
+            Self::ScopePanicked(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<JobPanic> for PoolError {
+    fn from(panic: JobPanic) -> Self {
+        Self::JobPanicked(panic)
+    }
+}
+
+/// Error returned when a job couldn't be submitted to the [ThreadPool](crate::ThreadPool)
+///
+/// Holds the job that was rejected, so it can be retried or run elsewhere.
+pub struct SubmitError<J> {
+    job: J,
+    error: PoolError,
+}
+
+impl<J> SubmitError<J> {
+    pub(crate) const fn new(job: J, error: PoolError) -> Self {
+        Self { job, error }
+    }
+
+    /// Returns the reason the job was rejected
+    pub const fn error(&self) -> &PoolError {
+        &self.error
+    }
+
+    /// Returns the rejected job
+    pub fn into_job(self) -> J {
+        self.job
+    }
+
+    /// Returns the rejected job, and the reason it was rejected
+    pub fn into_parts(self) -> (J, PoolError) {
+        (self.job, self.error)
+    }
+}
+
+impl<J> fmt::Debug for SubmitError<J> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SubmitError")
+         .field("job", &format_args!(".."))
+         .field("error", &self.error)
+         .finish()
+    }
+}
+
+impl<J> fmt::Display for SubmitError<J> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Couldn't submit the job: {}", self.error)
+    }
+}
+
+impl<J> std::error::Error for SubmitError<J> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl<J> From<SubmitError<J>> for PoolError {
+    fn from(err: SubmitError<J>) -> Self {
+        err.error
+    }
+}
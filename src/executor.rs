@@ -0,0 +1,187 @@
+//! A minimal executor to run futures on the [ThreadPool](crate::ThreadPool)
+
+use core::future::Future;
+use core::pin::{pin, Pin};
+use core::task::{Context, Poll, Waker};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::task::Wake;
+use std::thread::{self, Thread};
+use std::time::Duration;
+
+use crate::pool::PoolInner;
+use crate::worker::JobGuard;
+use crate::Counter;
+
+/// Runs a future to completion on the current thread
+///
+/// The thread is parked while the future is pending, until it's woken.
+/// Don't call this from inside a job, since it would block the worker.
+///
+/// # Example
+/// ```
+/// use job_pool::ThreadPool;
+///
+/// let pool = ThreadPool::default();
+/// let handle = pool.spawn_future(async { 2 + 2 });
+/// assert_eq!(job_pool::block_on(handle).unwrap(), 4);
+/// ```
+pub fn block_on<F: Future>(fut: F) -> F::Output {
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+        fn wake_by_ref(self: &Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let mut fut = pin!(fut);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(out) = fut.as_mut().poll(&mut cx) {
+            return out
+        }
+        thread::park();
+    }
+}
+
+/// Wraps a future, catching the panics of it's poll function
+pub(crate) struct CatchUnwind<F>(pub Pin<Box<F>>);
+
+impl<F: Future> Future for CatchUnwind<F> {
+    type Output = thread::Result<F::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let fut = self.0.as_mut();
+        match panic::catch_unwind(AssertUnwindSafe(|| fut.poll(cx))) {
+            Ok(Poll::Ready(out)) => Poll::Ready(Ok(out)),
+            Ok(Poll::Pending) => Poll::Pending,
+            Err(payload) => Poll::Ready(Err(payload)),
+        }
+    }
+}
+
+type BoxFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/* States of a Task */
+const IDLE: u8 = 0;
+const SCHEDULED: u8 = 1;
+const RUNNING: u8 = 2;
+/// Woken while running, must be polled again
+const NOTIFIED: u8 = 3;
+const DONE: u8 = 4;
+
+/// A future spawned in the pool
+///
+/// Every time it's woken, the task is sent to the pool
+/// to be polled by a worker.
+pub(crate) struct Task {
+    future: Mutex<Option<BoxFuture>>,
+    state: AtomicU8,
+    pool: Weak<PoolInner>,
+    global_counter: Counter,
+    /// Time spent polling the future, in nanoseconds
+    run_time: AtomicU64,
+}
+
+impl Task {
+    pub fn new(future: BoxFuture, pool: Weak<PoolInner>, global_counter: Counter) -> Arc<Self> {
+        Arc::new(Self {
+            future: Mutex::new(Some(future)),
+            state: AtomicU8::new(IDLE),
+            pool,
+            global_counter,
+            run_time: AtomicU64::new(0),
+        })
+    }
+
+    /// Sends the task to the pool, if it isn't already scheduled
+    pub fn schedule(self: &Arc<Self>) {
+        let mut state = self.state.load(Ordering::Acquire);
+        loop {
+            let next = match state {
+                IDLE => SCHEDULED,
+                RUNNING => NOTIFIED,
+                _ => return,
+            };
+            match self.state.compare_exchange(state, next, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => break,
+                Err(actual) => state = actual,
+            }
+        }
+        if state == IDLE {
+            self.send();
+        }
+    }
+
+    fn send(self: &Arc<Self>) {
+        let sent = match self.pool.upgrade() {
+            Some(pool) => pool.send_task(Arc::clone(self)),
+            None => false,
+        };
+        if !sent {
+            self.shut_down();
+        }
+    }
+
+    /// Drops the future of a task that can't be sent to the pool, because
+    /// it has been dropped. This completes it's handle with a
+    /// [PoolError::ShutDown](crate::PoolError::ShutDown), and releases
+    /// it's slot in the job counter.
+    fn shut_down(&self) {
+        let future = self.future.lock().unwrap().take();
+        if future.is_some() {
+            /* Set before dropping it, so waking it from it's drop is a no-op */
+            self.state.store(DONE, Ordering::Release);
+            let _ = panic::catch_unwind(AssertUnwindSafe(|| drop(future)));
+            self.global_counter.dec();
+        }
+    }
+
+    /// Polls the future. Called by the worker.
+    ///
+    /// Once the future completes, returns the [JobGuard] that
+    /// releases it's slot in the pool.
+    pub fn run(self: Arc<Self>) -> Option<JobGuard> {
+        self.state.store(RUNNING, Ordering::Release);
+        let waker = Waker::from(Arc::clone(&self));
+        let mut cx = Context::from_waker(&waker);
+
+        let mut future = self.future.lock().unwrap();
+        let fut = future.as_mut()?;
+        if fut.as_mut().poll(&mut cx).is_ready() {
+            *future = None;
+            self.state.store(DONE, Ordering::Release);
+            return Some(JobGuard { global_counter: self.global_counter.clone(), scope: None })
+        }
+        drop(future);
+
+        if self.state.compare_exchange(RUNNING, IDLE, Ordering::AcqRel, Ordering::Acquire).is_err() {
+            /* Woken while running */
+            self.state.store(SCHEDULED, Ordering::Release);
+            self.send();
+        }
+        None
+    }
+
+    /// Adds the time of a poll, and returns the total time spent polling the future
+    pub fn add_run_time(&self, elapsed: Duration) -> Duration {
+        let nanos = u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX);
+        let total = self.run_time.fetch_add(nanos, Ordering::Relaxed).saturating_add(nanos);
+        Duration::from_nanos(total)
+    }
+}
+
+impl Wake for Task {
+    fn wake(self: Arc<Self>) {
+        self.schedule();
+    }
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.schedule();
+    }
+}
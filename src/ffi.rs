@@ -15,6 +15,7 @@ impl PoolConfig {
             n_workers: self.n_workers,
             max_jobs: if self.max_jobs > 0 { Some(self.max_jobs as u16) } else { None },
             incoming_buf_size: if self.incoming_buf_size > 0 { Some(self.incoming_buf_size as u16) } else { None },
+            ..Default::default()
         }
     }
 }
@@ -23,7 +24,7 @@ impl PoolConfig {
 pub extern "C"
 fn pool_default_conf() -> PoolConfig {
     PoolConfig {
-        n_workers: 16,
+        n_workers: crate::PoolConfig::detect_workers().0,
         max_jobs: -1,
         incoming_buf_size: -1,
     }
@@ -0,0 +1,59 @@
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use crate::worker::Job;
+use crate::ThreadPool;
+
+/// Future returned by [ThreadPool::execute_async]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct ExecuteAsync<'pool, J> {
+    pool: &'pool ThreadPool,
+    job: Option<J>,
+}
+
+impl<'pool, J> ExecuteAsync<'pool, J> {
+    pub(crate) fn new(pool: &'pool ThreadPool, job: J) -> Self {
+        Self { pool, job: Some(job) }
+    }
+}
+
+impl<J> Unpin for ExecuteAsync<'_, J> {}
+
+impl<J: Job<'static>> Future for ExecuteAsync<'_, J> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.job.is_none() {
+            return Poll::Ready(())
+        }
+        match self.pool.poll_reserve(cx) {
+            Poll::Ready(()) => {
+                let job = self.job.take().unwrap();
+                self.pool.execute_reserved(Box::new(job));
+                Poll::Ready(())
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Future returned by [ThreadPool::join_async]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct JoinAsync<'pool> {
+    pool: &'pool ThreadPool,
+}
+
+impl<'pool> JoinAsync<'pool> {
+    pub(crate) fn new(pool: &'pool ThreadPool) -> Self {
+        Self { pool }
+    }
+}
+
+impl Future for JoinAsync<'_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        self.pool.poll_join(cx)
+    }
+}
@@ -0,0 +1,376 @@
+use core::any::Any;
+use core::cell::Cell;
+use core::fmt;
+use core::future::Future;
+use core::marker::PhantomData;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use core::time::Duration;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+use crate::scope::ScopeState;
+use crate::{PoolError, Result};
+
+/// The payload of a panicked job
+///
+/// Returned inside [PoolError::JobPanicked] by [JobHandle::join] when
+/// the job panicked instead of returning a value.
+pub struct JobPanic(Box<dyn Any + Send + 'static>);
+
+impl JobPanic {
+    pub(crate) fn new(payload: Box<dyn Any + Send + 'static>) -> Self {
+        Self(payload)
+    }
+
+    /// Returns the panic message, if the payload is a string
+    pub fn message(&self) -> Option<&str> {
+        if let Some(s) = self.0.downcast_ref::<&'static str>() {
+            Some(s)
+        } else {
+            self.0.downcast_ref::<String>().map(String::as_str)
+        }
+    }
+
+    /// Returns a reference to the panic payload
+    pub fn payload(&self) -> &(dyn Any + Send + 'static) {
+        &*self.0
+    }
+
+    /// Consumes self, returning the panic payload.
+    ///
+    /// This can be passed to [std::panic::resume_unwind]
+    /// to propagate the panic.
+    pub fn into_payload(self) -> Box<dyn Any + Send + 'static> {
+        self.0
+    }
+}
+
+impl fmt::Debug for JobPanic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("JobPanic")
+         .field(&self.message().unwrap_or("Box<dyn Any>"))
+         .finish()
+    }
+}
+
+impl fmt::Display for JobPanic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.message() {
+            Some(msg) => write!(f, "job panicked: {msg}"),
+            None => write!(f, "job panicked"),
+        }
+    }
+}
+
+impl std::error::Error for JobPanic {}
+
+/// Wraps the result of a job, turning a panic into a [PoolError]
+pub(crate) fn job_result<T>(result: thread::Result<T>) -> Result<T> {
+    result.map_err(|payload| PoolError::JobPanicked(JobPanic::new(payload)))
+}
+
+struct PacketState<T> {
+    result: Option<Result<T>>,
+    /// Set once the result has been taken
+    taken: bool,
+    /// Waker of the task awaiting the result, if any
+    waker: Option<Waker>,
+}
+
+/// Shared slot where a job stores its result
+pub(crate) struct Packet<T> {
+    state: Mutex<PacketState<T>>,
+    cvar: Condvar,
+    /// Scope that gets the panic of the job, if no one takes it
+    scope: Option<Arc<ScopeState>>,
+}
+
+impl<T> Packet<T> {
+    pub fn new() -> Self {
+        Self::with_scope(None)
+    }
+
+    /// Creates a packet for a job of `scope`. If the job panics, and it's
+    /// handle is dropped without joining it, the panic goes to the scope.
+    pub fn for_scope(scope: Arc<ScopeState>) -> Self {
+        Self::with_scope(Some(scope))
+    }
+
+    fn with_scope(scope: Option<Arc<ScopeState>>) -> Self {
+        Self {
+            state: Mutex::new(PacketState { result: None, taken: false, waker: None }),
+            cvar: Condvar::new(),
+            scope,
+        }
+    }
+
+    pub fn set(&self, result: Result<T>) {
+        let waker = {
+            let mut state = self.state.lock().unwrap();
+            state.result = Some(result);
+            state.waker.take()
+        };
+        self.cvar.notify_all();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    pub fn is_set(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        state.result.is_some() || state.taken
+    }
+
+    pub fn wait(&self) -> Result<T> {
+        let guard = self.state.lock().unwrap();
+        let mut guard = self.cvar.wait_while(guard, |s| s.result.is_none() && !s.taken).unwrap();
+        guard.take()
+    }
+
+    pub fn wait_timeout(&self, timeout: Duration) -> Option<Result<T>> {
+        let guard = self.state.lock().unwrap();
+        let (mut guard, _) = self.cvar.wait_timeout_while(guard, timeout, |s| s.result.is_none() && !s.taken).unwrap();
+        guard.is_set().then(|| guard.take())
+    }
+
+    /// Waits for the result to be set, without taking it.
+    /// Returns true if it's set.
+    pub fn wait_set_timeout(&self, timeout: Duration) -> bool {
+        let guard = self.state.lock().unwrap();
+        let (guard, _) = self.cvar.wait_timeout_while(guard, timeout, |s| s.result.is_none() && !s.taken).unwrap();
+        guard.is_set()
+    }
+
+    pub fn poll(&self, cx: &mut Context<'_>) -> Poll<Result<T>> {
+        let mut state = self.state.lock().unwrap();
+        if state.is_set() {
+            return Poll::Ready(state.take())
+        }
+        match &mut state.waker {
+            Some(waker) => waker.clone_from(cx.waker()),
+            waker => *waker = Some(cx.waker().clone()),
+        }
+        Poll::Pending
+    }
+}
+
+impl<T> Drop for Packet<T> {
+    fn drop(&mut self) {
+        let Some(scope) = &self.scope else { return };
+        let Ok(state) = self.state.get_mut() else { return };
+        if let Some(Err(PoolError::JobPanicked(panic))) = state.result.take() {
+            scope.panicked(panic);
+        }
+    }
+}
+
+impl<T> PacketState<T> {
+    fn is_set(&self) -> bool {
+        self.result.is_some() || self.taken
+    }
+
+    /// Takes the result
+    ///
+    /// # Panics
+    /// If it was already taken
+    fn take(&mut self) -> Result<T> {
+        assert!(!self.taken, "the result of the job was already taken");
+        self.taken = true;
+        self.result.take().unwrap()
+    }
+}
+
+thread_local! {
+    /// Set while a job is dropped by it's cancelled token
+    static CANCELLING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Drops a job cancelled by it's token
+///
+/// The [Completion]s dropped with it complete their packets
+/// with [PoolError::Cancelled] instead of [PoolError::ShutDown].
+pub(crate) fn drop_cancelled_job<J>(job: J) {
+    struct Reset;
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            CANCELLING.set(false);
+        }
+    }
+    CANCELLING.set(true);
+    let _reset = Reset;
+    drop(job);
+}
+
+/// Runs a job and stores it's result in a [Packet]
+///
+/// If it's dropped without running, the packet is completed with an
+/// error, so the handles don't wait forever. The error is
+/// [PoolError::Cancelled] if the job was dropped by it's cancelled
+/// token, and [PoolError::ShutDown] otherwise, e.g. if the pool
+/// was dropped before the job could be sent.
+pub(crate) struct Completion<T> {
+    packet: Option<Arc<Packet<T>>>,
+}
+
+impl<T> Completion<T> {
+    pub fn new(packet: Arc<Packet<T>>) -> Self {
+        Self { packet: Some(packet) }
+    }
+
+    pub fn run(self, f: impl FnOnce() -> T) {
+        self.complete(panic::catch_unwind(AssertUnwindSafe(f)));
+    }
+
+    pub fn complete(mut self, result: thread::Result<T>) {
+        if let Some(packet) = self.packet.take() {
+            packet.set(job_result(result));
+        }
+    }
+}
+
+impl<T> Drop for Completion<T> {
+    fn drop(&mut self) {
+        if let Some(packet) = self.packet.take() {
+            let error = if CANCELLING.get() {
+                PoolError::Cancelled
+            } else {
+                PoolError::ShutDown
+            };
+            packet.set(Err(error));
+        }
+    }
+}
+
+/// The second half of a [ThreadPool::join2](crate::ThreadPool::join2)
+///
+/// The job is run by whoever takes it first: a worker that
+/// received it, or the caller once it's done with the first half.
+pub(crate) struct Fork<F, T> {
+    job: Mutex<Option<F>>,
+    pub packet: Packet<T>,
+}
+
+impl<F: FnOnce() -> T, T> Fork<F, T> {
+    pub fn new(job: F) -> Self {
+        Self {
+            job: Mutex::new(Some(job)),
+            packet: Packet::new(),
+        }
+    }
+
+    /// Takes the job, if no one has taken it yet
+    pub fn take(&self) -> Option<F> {
+        self.job.lock().unwrap().take()
+    }
+
+    /// Runs the job if it hasn't been taken, and stores it's result
+    pub fn run(&self) {
+        if let Some(job) = self.take() {
+            self.packet.set(job_result(panic::catch_unwind(AssertUnwindSafe(job))));
+        }
+    }
+}
+
+/// A handle to a job spawned with [ThreadPool::spawn](crate::ThreadPool::spawn)
+///
+/// It can be used to wait for the job and get it's return value.
+///
+/// It also implements [Future], so it can be awaited from any async
+/// runtime without blocking the executor.
+///
+/// # Example
+/// ```
+/// use job_pool::ThreadPool;
+///
+/// # async fn example() {
+/// let pool = ThreadPool::default();
+/// let n = pool.spawn(|| 2 + 2).await.unwrap();
+/// assert_eq!(n, 4);
+/// # }
+/// ```
+pub struct JobHandle<T>(Arc<Packet<T>>);
+
+impl<T> JobHandle<T> {
+    pub(crate) fn new(packet: Arc<Packet<T>>) -> Self {
+        Self(packet)
+    }
+
+    /// Returns true if the job has finished
+    pub fn is_finished(&self) -> bool {
+        self.0.is_set()
+    }
+
+    /// Waits for the job to finish and returns it's result.
+    ///
+    /// # Errors
+    /// - [PoolError::JobPanicked] if the job panicked
+    /// - [PoolError::Cancelled] if the job was cancelled before it ran
+    ///
+    /// # Panics
+    /// If the result was already taken with [try_join](Self::try_join)
+    /// or [join_timeout](Self::join_timeout)
+    pub fn join(self) -> Result<T> {
+        self.0.wait()
+    }
+
+    /// Returns the result of the job if it has already finished,
+    /// or None if it's still running.
+    ///
+    /// # Panics
+    /// If the result was already taken
+    pub fn try_join(&mut self) -> Option<Result<T>> {
+        self.0.wait_timeout(Duration::ZERO)
+    }
+
+    /// Waits for the job to finish, for at most `timeout`.
+    ///
+    /// # Errors
+    /// - [PoolError::TimedOut] if the job didn't finish in time.
+    ///   The handle can still be joined later.
+    /// - [PoolError::JobPanicked] if the job panicked
+    ///
+    /// # Panics
+    /// If the result was already taken
+    pub fn join_timeout(&mut self, timeout: Duration) -> Result<T> {
+        self.0.wait_timeout(timeout).unwrap_or(Err(PoolError::TimedOut))
+    }
+}
+
+impl<T> Future for JobHandle<T> {
+    type Output = Result<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.0.poll(cx)
+    }
+}
+
+/// A handle to a job spawned with [Scope::spawn](crate::Scope::spawn)
+///
+/// Unlike [JobHandle], this handle can't outlive the `'scope`
+/// lifetime, since the job may borrow data from it.
+pub struct ScopedJoinHandle<'scope, T> {
+    packet: Arc<Packet<T>>,
+    _marker: PhantomData<&'scope ()>,
+}
+
+impl<T> ScopedJoinHandle<'_, T> {
+    pub(crate) fn new(packet: Arc<Packet<T>>) -> Self {
+        Self { packet, _marker: PhantomData }
+    }
+
+    /// Returns true if the job has finished
+    pub fn is_finished(&self) -> bool {
+        self.packet.is_set()
+    }
+
+    /// Waits for the job to finish and returns it's result.
+    ///
+    /// # Errors
+    /// - [PoolError::JobPanicked] if the job panicked
+    /// - [PoolError::Cancelled] if the scope was cancelled before the job ran
+    pub fn join(self) -> Result<T> {
+        self.packet.wait()
+    }
+}
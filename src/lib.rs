@@ -32,8 +32,6 @@
 //! pool.join();
 //! ```
 
-#![cfg_attr(feature = "use-nightly-mpmc", feature(mpmc_channel))]
-
 #[cfg(feature = "bindings")]
 mod ffi;
 
@@ -41,59 +39,147 @@ mod pool;
 mod worker;
 mod config;
 mod scope;
-pub use scope::Scope;
-
-/* Switch between mpsc and mpmc until
- * std::sync::mpmc is stabilized */
-
-#[cfg(feature = "use-nightly-mpmc")]
-#[path ="channel/mpmc.rs"]
+pub use scope::{Scope, ScopeError};
+mod handle;
+pub use handle::{JobHandle, JobPanic, ScopedJoinHandle};
 mod channel;
+mod priority;
+pub use priority::Priority;
+mod timer;
+pub use timer::{MissedTickPolicy, ScheduleHandle};
+mod cancel;
+pub use cancel::CancellationToken;
+mod executor;
+pub use executor::block_on;
+mod futures;
+pub use futures::{ExecuteAsync, JoinAsync};
+pub mod par;
+mod map;
+pub use map::{MapOrdered, MapUnordered};
+mod completion;
+pub use completion::CompletionQueue;
+mod stats;
+pub use stats::{Histogram, PoolStats, WorkerStats};
+mod affinity;
+pub use affinity::Affinity;
+mod sched;
+pub use sched::SchedPolicy;
+mod parallelism;
+pub use parallelism::ParallelismSource;
+mod error;
+pub use error::{PoolError, SubmitError, ThreadSetting};
 
-#[cfg(not(feature = "use-nightly-mpmc"))]
-#[path ="channel/mpsc.rs"]
-mod channel;
-
-use std::borrow::Cow;
 use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
 
 pub use pool::ThreadPool;
-pub use config::PoolConfig;
+pub use config::{PoolConfig, PanicPolicy, PanicHandler, Hooks, Hook, AfterJobHook, ThreadName};
+
+pub type Result<T> = std::result::Result<T,PoolError>;
+
+struct CounterState {
+    count: u16,
+    /// Tasks waiting for the count to decrease
+    wakers: Vec<Waker>,
+}
 
-pub type Result<T> = std::result::Result<T,Cow<'static,str>>;
+impl CounterState {
+    fn register(&mut self, waker: &Waker) {
+        if !self.wakers.iter().any(|w| w.will_wake(waker)) {
+            self.wakers.push(waker.clone());
+        }
+    }
+}
 
 #[derive(Clone)]
-struct Counter(Arc<(Mutex<u16>,Condvar)>);
+struct Counter(Arc<(Mutex<CounterState>,Condvar)>);
 
 impl Counter {
     pub fn new() -> Self {
-        Self(Arc::new((Mutex::new(0), Condvar::new())))
+        let state = CounterState { count: 0, wakers: Vec::new() };
+        Self(Arc::new((Mutex::new(state), Condvar::new())))
     }
 
     pub fn inc(&self, max: Option<u16>) {
         let (lock,cvar) = &*self.0;
         let mut counter = lock.lock().unwrap();
         if let Some(max) = max {
-            counter = cvar.wait_while(counter, |n| *n >= max).unwrap();
+            counter = cvar.wait_while(counter, |c| c.count >= max).unwrap();
+        }
+        counter.count += 1;
+
+    }
+
+    /// Like [inc](Self::inc), but waits until `deadline` at most.
+    /// Returns false if the count didn't go below `max` in time.
+    pub fn inc_until(&self, max: Option<u16>, deadline: Instant) -> bool {
+        let (lock,cvar) = &*self.0;
+        let mut counter = lock.lock().unwrap();
+        if let Some(max) = max {
+            while counter.count >= max {
+                let timeout = deadline.saturating_duration_since(Instant::now());
+                if timeout.is_zero() {
+                    return false
+                }
+                counter = cvar.wait_timeout(counter, timeout).unwrap().0;
+            }
         }
-        *counter += 1;
+        counter.count += 1;
+        true
+    }
 
+    /// Like [inc](Self::inc), but registers the waker
+    /// instead of blocking if the count is at `max`
+    pub fn poll_inc(&self, cx: &mut Context<'_>, max: Option<u16>) -> Poll<()> {
+        let mut counter = self.0.0.lock().unwrap();
+        if max.is_some_and(|max| counter.count >= max) {
+            counter.register(cx.waker());
+            return Poll::Pending
+        }
+        counter.count += 1;
+        Poll::Ready(())
     }
 
     pub fn count(&self) -> u16 {
-        *self.0.0.lock().unwrap()
+        self.0.0.lock().unwrap().count
     }
 
     pub fn dec(&self) {
         let (lock,condv) = &*self.0;
-        let mut counter = lock.lock().unwrap();
-        *counter -= 1;
-        condv.notify_one();
+        let wakers = {
+            let mut counter = lock.lock().unwrap();
+            counter.count -= 1;
+            core::mem::take(&mut counter.wakers)
+        };
+        condv.notify_all();
+        wakers.into_iter().for_each(Waker::wake);
     }
 
     pub fn join(&self) {
         let (lock,cvar) = &*self.0;
         let counter = lock.lock().unwrap();
-        let _lock = cvar.wait_while(counter, |n| *n > 0).unwrap();
+        let _lock = cvar.wait_while(counter, |c| c.count > 0).unwrap();
+    }
+
+    /// Like [join](Self::join), but waits for at most `timeout`.
+    /// Returns false if the count didn't reach 0 in time.
+    pub fn join_timeout(&self, timeout: Duration) -> bool {
+        let (lock,cvar) = &*self.0;
+        let counter = lock.lock().unwrap();
+        let (_lock, res) = cvar.wait_timeout_while(counter, timeout, |c| c.count > 0).unwrap();
+        !res.timed_out()
+    }
+
+    /// Like [join](Self::join), but registers the waker
+    /// instead of blocking if the count is not 0
+    pub fn poll_join(&self, cx: &mut Context<'_>) -> Poll<()> {
+        let mut counter = self.0.0.lock().unwrap();
+        if counter.count > 0 {
+            counter.register(cx.waker());
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
     }
 }
@@ -0,0 +1,89 @@
+use core::iter::Fuse;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use crate::{CompletionQueue, JobHandle, Result, ThreadPool};
+
+/// Iterator returned by [ThreadPool::map_ordered]
+///
+/// Yields the results in the same order as the inputs.
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct MapOrdered<'pool, I, F, T> {
+    pool: &'pool ThreadPool,
+    iter: Fuse<I>,
+    f: Arc<F>,
+    max_in_flight: usize,
+    in_flight: VecDeque<JobHandle<T>>,
+}
+
+impl<'pool, I: Iterator, F, T> MapOrdered<'pool, I, F, T> {
+    pub(crate) fn new(pool: &'pool ThreadPool, iter: I, max_in_flight: usize, f: F) -> Self {
+        Self {
+            pool,
+            iter: iter.fuse(),
+            f: Arc::new(f),
+            max_in_flight: max_in_flight.max(1),
+            in_flight: VecDeque::new(),
+        }
+    }
+}
+
+impl<I, F, T> Iterator for MapOrdered<'_, I, F, T>
+where
+    I: Iterator,
+    I::Item: Send + 'static,
+    F: Fn(I::Item) -> T + Send + Sync + 'static,
+    T: Send + 'static,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.in_flight.len() < self.max_in_flight {
+            let Some(item) = self.iter.next() else { break };
+            let f = Arc::clone(&self.f);
+            self.in_flight.push_back(self.pool.spawn(move || f(item)));
+        }
+        self.in_flight.pop_front().map(JobHandle::join)
+    }
+}
+
+/// Iterator returned by [ThreadPool::map_unordered]
+///
+/// Yields the results as the jobs finish.
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct MapUnordered<'pool, I, F, T> {
+    iter: Fuse<I>,
+    f: Arc<F>,
+    max_in_flight: usize,
+    queue: CompletionQueue<'pool, T>,
+}
+
+impl<'pool, I: Iterator, F, T: Send + 'static> MapUnordered<'pool, I, F, T> {
+    pub(crate) fn new(pool: &'pool ThreadPool, iter: I, max_in_flight: usize, f: F) -> Self {
+        Self {
+            iter: iter.fuse(),
+            f: Arc::new(f),
+            max_in_flight: max_in_flight.max(1),
+            queue: CompletionQueue::new(pool),
+        }
+    }
+}
+
+impl<I, F, T> Iterator for MapUnordered<'_, I, F, T>
+where
+    I: Iterator,
+    I::Item: Send + 'static,
+    F: Fn(I::Item) -> T + Send + Sync + 'static,
+    T: Send + 'static,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.queue.pending() < self.max_in_flight {
+            let Some(item) = self.iter.next() else { break };
+            let f = Arc::clone(&self.f);
+            self.queue.submit(move || f(item));
+        }
+        self.queue.next()
+    }
+}
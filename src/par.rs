@@ -0,0 +1,184 @@
+//! Parallel iterators
+//!
+//! A [ParIter] splits a slice in chunks, and runs them in parallel by
+//! halving the slice recursively with [ThreadPool::join2]. The number
+//! of chunks depends on the number of workers of the pool.
+//!
+//! Since a worker keeps running other jobs while it waits in `join2`,
+//! a [ParIter] can also be used from inside a job.
+//!
+//! # Example
+//! ```
+//! use job_pool::ThreadPool;
+//!
+//! let pool = ThreadPool::default();
+//! let nums: Vec<u64> = (0..1000).collect();
+//!
+//! let squares: Vec<u64> = pool.par_iter(&nums).map(|n| n * n).collect();
+//! assert_eq!(squares[10], 100);
+//!
+//! let even: u64 = pool.par_iter(&nums).filter(|n| *n % 2 == 0).copied().sum();
+//! assert_eq!(even, 249500);
+//! ```
+
+use core::iter::Sum;
+use core::marker::PhantomData;
+use crate::ThreadPool;
+
+/// Number of chunks per worker. More chunks than workers
+/// helps balancing the load when some chunks are slower.
+const CHUNKS_PER_WORKER: usize = 4;
+
+/// A parallel iterator over a slice
+///
+/// Created by [ThreadPool::par_iter]. `F` maps each element of the
+/// slice to an output, or None if it's filtered out.
+pub struct ParIter<'pool, 'data, T, O, F> {
+    pool: &'pool ThreadPool,
+    slice: &'data [T],
+    f: F,
+    _marker: PhantomData<fn() -> O>,
+}
+
+/// The [ParIter] returned by [ThreadPool::par_iter]
+pub type Iter<'pool, 'data, T> = ParIter<'pool, 'data, T, &'data T, fn(&'data T) -> Option<&'data T>>;
+
+impl<'pool, 'data, T: Sync> Iter<'pool, 'data, T> {
+    pub(crate) fn new(pool: &'pool ThreadPool, slice: &'data [T]) -> Self {
+        Self { pool, slice, f: Some, _marker: PhantomData }
+    }
+}
+
+impl<'pool, 'data, T, O, F> ParIter<'pool, 'data, T, O, F>
+where
+    T: Sync,
+    F: Fn(&'data T) -> Option<O> + Sync,
+{
+    /// Applies `g` to each element
+    pub fn map<U, G>(self, g: G) -> ParIter<'pool, 'data, T, U, impl Fn(&'data T) -> Option<U> + Sync>
+    where
+        G: Fn(O) -> U + Sync,
+    {
+        let f = self.f;
+        ParIter {
+            pool: self.pool,
+            slice: self.slice,
+            f: move |t| f(t).map(&g),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Keeps only the elements for which `p` returns true
+    pub fn filter<P>(self, p: P) -> ParIter<'pool, 'data, T, O, impl Fn(&'data T) -> Option<O> + Sync>
+    where
+        P: Fn(&O) -> bool + Sync,
+    {
+        let f = self.f;
+        ParIter {
+            pool: self.pool,
+            slice: self.slice,
+            f: move |t| f(t).filter(&p),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Runs `g` on each chunk of the slice, and returns
+    /// the results in the same order as the chunks.
+    fn run<R, G>(&self, g: G) -> Vec<R>
+    where
+        R: Send,
+        G: Fn(&mut dyn Iterator<Item = O>) -> R + Sync,
+    {
+        let n_chunks = self.pool.n_workers().max(1) * CHUNKS_PER_WORKER;
+        let chunk_len = self.slice.len().div_ceil(n_chunks).max(1);
+        let (f, g) = (&self.f, &g);
+        split(self.pool, self.slice, chunk_len, &|chunk| g(&mut chunk.iter().filter_map(f)))
+    }
+
+    /// Calls `g` on each element
+    pub fn for_each<G>(self, g: G)
+    where
+        G: Fn(O) + Sync,
+    {
+        self.run(|it| it.for_each(&g));
+    }
+
+    /// Collects the elements, in the same order as the slice
+    pub fn collect<C>(self) -> C
+    where
+        O: Send,
+        C: FromIterator<O>,
+    {
+        self.run(|it| it.collect::<Vec<_>>())
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+
+    /// Reduces the elements with `op`.
+    ///
+    /// `identity` is called to get the initial value of each chunk,
+    /// so it may be called more than once. Returns `identity()` if
+    /// there are no elements.
+    pub fn reduce<ID, OP>(self, identity: ID, op: OP) -> O
+    where
+        O: Send,
+        ID: Fn() -> O + Sync,
+        OP: Fn(O, O) -> O + Sync,
+    {
+        self.run(|it| it.fold(identity(), &op))
+            .into_iter()
+            .fold(identity(), &op)
+    }
+
+    /// Sums the elements
+    pub fn sum<S>(self) -> S
+    where
+        S: Send + Sum<O> + Sum<S>,
+    {
+        self.run(|it| it.sum::<S>())
+            .into_iter()
+            .sum()
+    }
+}
+
+/// Runs `run` on each chunk of `slice`, splitting it in
+/// halves with [ThreadPool::join2] until a chunk is left.
+fn split<'data, T, R>(pool: &ThreadPool, slice: &'data [T], chunk_len: usize, run: &(dyn Fn(&'data [T]) -> R + Sync)) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+{
+    if slice.is_empty() {
+        return Vec::new()
+    }
+    if slice.len() <= chunk_len {
+        return vec![run(slice)]
+    }
+    let mid = slice.len().div_ceil(chunk_len) / 2 * chunk_len;
+    let (l, r) = slice.split_at(mid);
+    let (mut l, r) = pool.join2(|| split(pool, l, chunk_len, run),
+                                || split(pool, r, chunk_len, run));
+    l.extend(r);
+    l
+}
+
+impl<'pool, 'data, T, O, F> ParIter<'pool, 'data, T, &'data O, F>
+where
+    T: Sync,
+    O: Clone + 'data,
+    F: Fn(&'data T) -> Option<&'data O> + Sync,
+{
+    /// Clones each element
+    pub fn cloned(self) -> ParIter<'pool, 'data, T, O, impl Fn(&'data T) -> Option<O> + Sync> {
+        self.map(O::clone)
+    }
+
+    /// Copies each element
+    pub fn copied(self) -> ParIter<'pool, 'data, T, O, impl Fn(&'data T) -> Option<O> + Sync>
+    where
+        O: Copy,
+    {
+        self.map(|o| *o)
+    }
+}
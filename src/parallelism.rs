@@ -0,0 +1,194 @@
+//! Detection of the number of CPUs available to the process
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+/// Number of workers used when the CPUs can't be detected
+pub(crate) const DEFAULT_WORKERS: u16 = 16;
+
+/// Source that decided the number of workers of [PoolConfig::auto](crate::PoolConfig::auto)
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum ParallelismSource {
+    /// The CPU quota of the cgroup v2 of the process (`cpu.max`)
+    CgroupV2,
+    /// The CPU quota of the cgroup v1 of the process (`cpu.cfs_quota_us`)
+    CgroupV1,
+    /// The CPU affinity mask of the process
+    Affinity,
+    /// [std::thread::available_parallelism]
+    AvailableParallelism,
+    /// None of the above could be read, so the default was used
+    Default,
+}
+
+/// Returns the number of CPUs available to the process, and
+/// the source that decided it. If more than one source applies,
+/// the lowest one is used.
+pub(crate) fn detect() -> (u16, ParallelismSource) {
+    let candidates = [
+        cgroup_v2_quota().map(|n| (n, ParallelismSource::CgroupV2)),
+        cgroup_v1_quota().map(|n| (n, ParallelismSource::CgroupV1)),
+        affinity_cpus().map(|n| (n, ParallelismSource::Affinity)),
+        thread::available_parallelism().ok().map(|n| (n.get(), ParallelismSource::AvailableParallelism)),
+    ];
+    candidates.into_iter()
+              .flatten()
+              .filter(|(n, _)| *n > 0)
+              .min_by_key(|(n, _)| *n)
+              .map(|(n, source)| (u16::try_from(n).unwrap_or(u16::MAX), source))
+              .unwrap_or((DEFAULT_WORKERS, ParallelismSource::Default))
+}
+
+fn affinity_cpus() -> Option<usize> {
+    crate::affinity::get_affinity().ok().map(|cpus| cpus.len())
+}
+
+/// Returns the cgroup path of the process for the given v1
+/// controller, or the v2 path if `controller` is None.
+fn cgroup_path(controller: Option<&str>) -> Option<PathBuf> {
+    let cgroups = fs::read_to_string("/proc/self/cgroup").ok()?;
+    parse_cgroup_path(&cgroups, controller)
+}
+
+/// Finds the path of the given controller in the contents of `/proc/self/cgroup`
+fn parse_cgroup_path(cgroups: &str, controller: Option<&str>) -> Option<PathBuf> {
+    cgroups.lines().find_map(|line| {
+        let mut fields = line.splitn(3, ':');
+        let (_, controllers, path) = (fields.next()?, fields.next()?, fields.next()?);
+        let found = match controller {
+            Some(c) => controllers.split(',').any(|name| name == c),
+            None => controllers.is_empty(),
+        };
+        found.then(|| PathBuf::from(path.trim_start_matches('/')))
+    })
+}
+
+/// Returns the number of CPUs allowed by a quota of `quota` every `period`
+fn quota_cpus(quota: u64, period: u64) -> Option<usize> {
+    (period > 0).then(|| quota.div_ceil(period).max(1) as usize)
+}
+
+/// Parses the contents of a cgroup v2 `cpu.max` file
+fn parse_cpu_max(max: &str) -> Option<usize> {
+    let mut fields = max.split_whitespace();
+    let (quota, period) = (fields.next()?, fields.next()?);
+    /* A quota of max means there's no limit */
+    if quota == "max" {
+        return None
+    }
+    quota_cpus(quota.parse().ok()?, period.parse().ok()?)
+}
+
+/// Parses the contents of the cgroup v1 `cpu.cfs_quota_us`
+/// and `cpu.cfs_period_us` files
+fn parse_cfs_quota(quota: &str, period: &str) -> Option<usize> {
+    let quota: i64 = quota.trim().parse().ok()?;
+    let period: u64 = period.trim().parse().ok()?;
+    /* A quota of -1 means there's no limit */
+    quota_cpus(u64::try_from(quota).ok()?, period)
+}
+
+/// Finds the cgroup directory at `path` that contains `file`, under any
+/// of the `mounts`. Returns the mount, and the directory.
+///
+/// Inside a container the cgroup is usually mounted at it's root, so
+/// if it's not found under `path`, it's looked for in the root too.
+fn cgroup_dir<'a>(mounts: &[&'a str], path: &Path, file: &str) -> Option<(&'a Path, PathBuf)> {
+    mounts.iter()
+          .map(|mount| Path::new(*mount))
+          .flat_map(|mount| [(mount, mount.join(path)), (mount, mount.to_path_buf())])
+          .find(|(_, dir)| dir.join(file).is_file())
+}
+
+/// Returns `dir` and it's parents, up to the `mount` of the cgroup hierarchy
+fn cgroup_ancestors<'a>(mount: &'a Path, dir: &'a Path) -> impl Iterator<Item = &'a Path> {
+    dir.ancestors().take_while(move |dir| dir.starts_with(mount))
+}
+
+/// Returns the lowest quota of the cgroup at `path` and it's parents.
+///
+/// A parent cgroup may have a lower quota than the process' own
+/// one, which limits the process too.
+fn cgroup_quota(mounts: &[&str], path: &Path, file: &str, quota: impl Fn(&Path) -> Option<usize>) -> Option<usize> {
+    let (mount, dir) = cgroup_dir(mounts, path, file)?;
+    cgroup_ancestors(mount, &dir).filter_map(quota).min()
+}
+
+fn read(dir: &Path, file: &str) -> Option<String> {
+    fs::read_to_string(dir.join(file)).ok()
+}
+
+fn cgroup_v2_quota() -> Option<usize> {
+    let path = cgroup_path(None)?;
+    cgroup_quota(&["/sys/fs/cgroup", "/sys/fs/cgroup/unified"], &path, "cpu.max", |dir| {
+        parse_cpu_max(&read(dir, "cpu.max")?)
+    })
+}
+
+fn cgroup_v1_quota() -> Option<usize> {
+    let path = cgroup_path(Some("cpu"))?;
+    cgroup_quota(&["/sys/fs/cgroup/cpu", "/sys/fs/cgroup/cpu,cpuacct"], &path, "cpu.cfs_quota_us", |dir| {
+        parse_cfs_quota(&read(dir, "cpu.cfs_quota_us")?, &read(dir, "cpu.cfs_period_us")?)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CGROUPS_V1: &str = "\
+12:memory:/docker/abc123
+4:cpu,cpuacct:/docker/abc123
+1:name=systemd:/docker/abc123
+0::/
+";
+
+    const CGROUPS_V2: &str = "0::/user.slice/user-1000.slice/session-2.scope\n";
+
+    #[test]
+    fn cgroup_paths() {
+        assert_eq!(parse_cgroup_path(CGROUPS_V1, Some("cpu")), Some(PathBuf::from("docker/abc123")));
+        assert_eq!(parse_cgroup_path(CGROUPS_V1, Some("cpuacct")), Some(PathBuf::from("docker/abc123")));
+        assert_eq!(parse_cgroup_path(CGROUPS_V1, Some("cpuset")), None);
+        assert_eq!(parse_cgroup_path(CGROUPS_V1, None), Some(PathBuf::new()));
+        assert_eq!(parse_cgroup_path(CGROUPS_V2, None),
+                   Some(PathBuf::from("user.slice/user-1000.slice/session-2.scope")));
+        assert_eq!(parse_cgroup_path(CGROUPS_V2, Some("cpu")), None);
+        assert_eq!(parse_cgroup_path("", None), None);
+    }
+
+    #[test]
+    fn cpu_max() {
+        assert_eq!(parse_cpu_max("200000 100000\n"), Some(2));
+        assert_eq!(parse_cpu_max("150000 100000"), Some(2));
+        assert_eq!(parse_cpu_max("1000 100000"), Some(1));
+        assert_eq!(parse_cpu_max("max 100000\n"), None);
+        assert_eq!(parse_cpu_max("100000 0"), None);
+        assert_eq!(parse_cpu_max("100000"), None);
+        assert_eq!(parse_cpu_max(""), None);
+    }
+
+    #[test]
+    fn cfs_quota() {
+        assert_eq!(parse_cfs_quota("400000\n", "100000\n"), Some(4));
+        assert_eq!(parse_cfs_quota("50000", "100000"), Some(1));
+        assert_eq!(parse_cfs_quota("-1\n", "100000\n"), None);
+        assert_eq!(parse_cfs_quota("100000", "0"), None);
+        assert_eq!(parse_cfs_quota("abc", "100000"), None);
+    }
+
+    #[test]
+    fn nested_cgroups() {
+        let mount = Path::new("/sys/fs/cgroup");
+        let dir = mount.join("kubepods.slice/pod1/container1");
+        let dirs: Vec<&Path> = cgroup_ancestors(mount, &dir).collect();
+        assert_eq!(dirs, [
+            Path::new("/sys/fs/cgroup/kubepods.slice/pod1/container1"),
+            Path::new("/sys/fs/cgroup/kubepods.slice/pod1"),
+            Path::new("/sys/fs/cgroup/kubepods.slice"),
+            Path::new("/sys/fs/cgroup"),
+        ]);
+        assert_eq!(cgroup_ancestors(mount, mount).count(), 1);
+    }
+}
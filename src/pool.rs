@@ -1,7 +1,23 @@
-use crate::scope::Scope;
-use crate::worker::{Job, Worker, Message};
-use crate::{channel, Counter, PoolConfig, Result};
-use crate::channel::SenderWrapper;
+use core::future::Future;
+use core::mem;
+use core::task::{Context, Poll};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::executor::{CatchUnwind, Task};
+use crate::futures::{ExecuteAsync, JoinAsync};
+use crate::affinity::Placement;
+use crate::par;
+use crate::stats::{Metrics, PoolStats};
+use crate::map::{MapOrdered, MapUnordered};
+use crate::handle::{Completion, Fork, JobHandle, Packet};
+use crate::scope::{Scope, ScopeState};
+use crate::timer::{MissedTickPolicy, ScheduleHandle, Timer};
+use crate::worker::{self, Cancellable, Job, JobGuard, Worker, Message, Shared};
+use crate::{channel, config, CancellationToken, Counter, PoolConfig, PoolError, Priority, Result, SubmitError};
+use crate::channel::{ReceiverWrapper, SendError, SenderWrapper, TrySendError};
 
 /// Thread Pool
 ///
@@ -16,8 +32,18 @@ use crate::channel::SenderWrapper;
 /// pool.execute(|| println!("Hello world!"));
 /// ```
 pub struct ThreadPool {
-    workers: Vec<Worker>,
+    inner: Arc<PoolInner>,
+    /// Started the first time a job is scheduled
+    timer: OnceLock<Timer>,
+}
+
+/// State of the [ThreadPool], shared with the [Timer] thread
+pub(crate) struct PoolInner {
+    /// Handles of the spawned workers, some of them may be retiring
+    workers: Mutex<Vec<Worker>>,
+    shared: Arc<Shared>,
     sender: SenderWrapper<Message>,
+    receiver: ReceiverWrapper<Message>,
     job_count: Counter,
     max_jobs: Option<u16>,
 }
@@ -26,32 +52,56 @@ impl ThreadPool {
     /// Creates a new `ThreadPool`
     ///
     /// # Errors
-    /// If the [PoolConfig] is not valid
+    /// If the [PoolConfig] is not valid, or a worker thread couldn't be spawned
     pub fn new(config: PoolConfig) -> Result<ThreadPool> {
         config.validate()?;
 
         let size = config.n_workers as usize;
         let (sender,receiver) =
             if let Some(max) = config.incoming_buf_size {
-                channel::sync_channel(max as usize)
+                channel::sync_channel(max as usize, config.aging)
             } else {
-                channel::channel()
+                channel::channel(config.aging)
             };
-        let mut workers = Vec::with_capacity(size);
-        for _ in 0..size-1 {
-            let worker = Worker::new(receiver.clone());
-            workers.push(worker);
-        }
-        let worker = Worker::new(receiver);
-        workers.push(worker);
+        let min_workers = config.min_workers() as usize;
+        let max_workers = config.max_workers() as usize;
+        let shared = Arc::new(Shared {
+            active: AtomicUsize::new(0),
+            idle: AtomicUsize::new(0),
+            alive: AtomicUsize::new(0),
+            min_workers,
+            max_workers,
+            keep_alive: (min_workers < max_workers.max(size)).then_some(config.keep_alive),
+            panic_policy: config.panic_policy,
+            hooks: config.hooks,
+            thread_name: config.thread_name,
+            stack_size: config.stack_size,
+            placement: Placement::new(&config.affinity),
+            sched_policy: config.sched_policy,
+            nice: config.nice,
+            next_index: AtomicUsize::new(0),
+            resize: Mutex::new(()),
+            closed: AtomicBool::new(false),
+            metrics: Metrics::new(),
+        });
 
         let global = Counter::new();
-        Ok(ThreadPool {
-            workers,
+        let inner = PoolInner {
+            workers: Mutex::new(Vec::with_capacity(size)),
+            shared,
             job_count: global,
             max_jobs: config.max_jobs,
             sender,
-        })
+            receiver,
+        };
+        let pool = ThreadPool {
+            inner: Arc::new(inner),
+            timer: OnceLock::new(),
+        };
+        /* If a worker fails to spawn, dropping the pool
+         * shuts down the ones that did spawn. */
+        pool.set_workers(config.n_workers)?;
+        Ok(pool)
     }
     /// Create a [ThreadPool] with the default [configuration](PoolConfig)
     #[inline]
@@ -68,21 +118,120 @@ impl ThreadPool {
         Self::new(conf)
     }
 
+    /// Returns a snapshot of the [statistics](PoolStats) of this pool
+    ///
+    /// # Example
+    /// ```
+    /// use job_pool::ThreadPool;
+    ///
+    /// let pool = ThreadPool::with_size(2).unwrap();
+    /// pool.execute(|| println!("Hello"));
+    /// pool.join();
+    ///
+    /// let stats = pool.stats();
+    /// assert_eq!(stats.submitted, 1);
+    /// assert_eq!(stats.completed, 1);
+    /// assert_eq!(stats.workers.len(), 2);
+    /// ```
+    pub fn stats(&self) -> PoolStats {
+        let inner = &self.inner;
+        let metrics = &inner.shared.metrics;
+        let workers = inner.workers.lock().unwrap()
+                           .iter()
+                           .filter(|w| !w.is_finished())
+                           .map(Worker::stats)
+                           .collect();
+        PoolStats {
+            submitted: metrics.submitted.load(Ordering::Relaxed),
+            completed: metrics.completed.load(Ordering::Relaxed),
+            panicked: metrics.panicked.load(Ordering::Relaxed),
+            cancelled: metrics.cancelled.load(Ordering::Relaxed),
+            queue_depth: inner.sender.queued(),
+            busy_workers: metrics.busy.load(Ordering::Relaxed),
+            workers,
+            queue_wait: metrics.queue_wait.snapshot(),
+            run_time: metrics.run_time.snapshot(),
+        }
+    }
+
     /// Returns the number of pending jobs
     pub fn pending_jobs(&self) -> usize {
-        self.job_count.count() as usize
+        self.inner.job_count.count() as usize
     }
 
-    pub(crate) fn execute_inside_scope(&self, job: Box<dyn Job<'static>>, scope_counter: Counter) {
-        self.job_count.inc(self.max_jobs);
-        scope_counter.inc(None);
+    /// Returns the number of workers of the pool
+    ///
+    /// This doesn't count workers that are being retired by
+    /// [set_workers](Self::set_workers), but haven't exited yet.
+    ///
+    /// This number can change over time, if the pool is configured
+    /// with [min_workers](PoolConfig::min_workers) or [max_workers](PoolConfig::max_workers)
+    pub fn n_workers(&self) -> usize {
+        self.inner.shared.active.load(Ordering::Acquire)
+    }
 
-        let msg = Message::Job {
-            job: Box::new(job),
-            global_counter: self.job_count.clone(),
-            scope_counter: Some(scope_counter),
-        };
-        self.sender.send(msg).unwrap()
+    /// Changes the number of workers of the pool
+    ///
+    /// If `n` is greater than the current number of workers,
+    /// new workers are spawned. Otherwise, the excess workers are
+    /// told to shut down. A worker being retired will exit after
+    /// finishing it's current job, and the queued jobs are still run
+    /// by the remaining workers.
+    ///
+    /// If the pool has [min_workers](PoolConfig::min_workers) or
+    /// [max_workers](PoolConfig::max_workers) configured, the number of
+    /// workers will still grow or shrink from `n` as the load changes.
+    ///
+    /// # Errors
+    /// If `n` is not a valid number of workers for this pool
+    /// (see [PoolConfig::validate]), or a worker thread couldn't
+    /// be spawned.
+    ///
+    /// # Example
+    /// ```
+    /// use job_pool::ThreadPool;
+    ///
+    /// let pool = ThreadPool::with_size(4).unwrap();
+    /// pool.set_workers(8).unwrap();
+    /// assert_eq!(pool.n_workers(), 8);
+    /// pool.set_workers(2).unwrap();
+    /// assert_eq!(pool.n_workers(), 2);
+    /// ```
+    pub fn set_workers(&self, n: u16) -> Result<()> {
+        config::validate_workers(n, self.inner.max_jobs)?;
+
+        let n = n as usize;
+        let mut workers = self.inner.workers.lock().unwrap();
+        let _resize = self.inner.shared.resize.lock().unwrap();
+        reap_finished(&mut workers);
+
+        let active = self.inner.shared.active.swap(n, Ordering::AcqRel);
+        if n > active {
+            for i in active..n {
+                match Worker::new(self.inner.receiver.clone(), Arc::clone(&self.inner.shared)) {
+                    Ok(worker) => workers.push(worker),
+                    Err(err) => {
+                        self.inner.shared.active.fetch_sub(n - i, Ordering::AcqRel);
+                        return Err(err)
+                    }
+                }
+            }
+        } else {
+            for _ in n..active {
+                self.inner.sender.send_control(Message::Shutdown);
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn execute_inside_scope(
+        &self,
+        job: Box<dyn Job<'static>>,
+        scope: Arc<ScopeState>,
+        priority: Priority,
+        token: CancellationToken,
+    ) {
+        self.inner.execute_inside_scope(job, scope, priority, token);
     }
 
     /// Executes the given job inside this pool.
@@ -106,13 +255,231 @@ impl ThreadPool {
     /// });
     /// ```
     pub fn execute(&self, job: impl Job<'static>) {
-        self.job_count.inc(self.max_jobs);
-        let msg = Message::Job {
-            job: Box::new(job),
-            global_counter: self.job_count.clone(),
-            scope_counter: None
+        self.execute_with_priority(Priority::Normal, job);
+    }
+
+    /// Executes the given job inside this pool, with the given [Priority].
+    ///
+    /// Workers always take the highest priority job available. If the pool
+    /// is configured with an [aging](PoolConfig::aging) period, lower priority
+    /// jobs that have been waiting for too long are run first.
+    ///
+    /// # Example
+    /// ```
+    /// use job_pool::{Priority, ThreadPool};
+    ///
+    /// let pool = ThreadPool::default();
+    /// pool.execute_with_priority(Priority::Low, || println!("Bulk job"));
+    /// pool.execute_with_priority(Priority::High, || println!("Urgent job"));
+    /// ```
+    pub fn execute_with_priority(&self, priority: Priority, job: impl Job<'static>) {
+        self.inner.execute(Box::new(job), priority, None);
+    }
+
+    /// Executes the given job inside this pool, with the given [CancellationToken].
+    ///
+    /// If the token is cancelled before the job starts running, the job
+    /// is dropped without running. It still counts as finished for
+    /// [pending_jobs](Self::pending_jobs) and [join](Self::join).
+    pub fn execute_with_token(&self, token: CancellationToken, job: impl Job<'static>) {
+        self.inner.execute(Box::new(job), Priority::Normal, Some(token));
+    }
+
+    /// Executes the given job inside this pool, or returns it back if
+    /// the pool can't run it.
+    ///
+    /// Like [execute](Self::execute), this blocks while the pool is at it's
+    /// [max_jobs](PoolConfig::max_jobs) or [incoming_buf_size](PoolConfig::incoming_buf_size).
+    ///
+    /// # Errors
+    /// [PoolError::ShutDown] if all the workers have exited, and
+    /// no new one could be spawned.
+    ///
+    /// # Example
+    /// ```
+    /// use job_pool::ThreadPool;
+    ///
+    /// let pool = ThreadPool::default();
+    /// if let Err(err) = pool.try_execute(|| println!("Hello")) {
+    ///     eprintln!("{err}");
+    ///     (err.into_job())();
+    /// }
+    /// ```
+    pub fn try_execute<J: Job<'static>>(&self, job: J) -> std::result::Result<(), SubmitError<J>> {
+        self.inner.submit(job, None)
+    }
+
+    /// Like [try_execute](Self::try_execute), but gives up if the
+    /// pool is still full after `timeout`.
+    ///
+    /// # Errors
+    /// - [PoolError::TimedOut] if there was no space for the job in time
+    /// - [PoolError::ShutDown] if all the workers have exited
+    ///
+    /// # Example
+    /// ```
+    /// use job_pool::{PoolConfig, ThreadPool};
+    /// use std::time::Duration;
+    ///
+    /// let conf = PoolConfig::builder().max_jobs(16).build();
+    /// let pool = ThreadPool::new(conf).unwrap();
+    /// pool.execute_timeout(|| println!("Hello"), Duration::from_millis(100)).unwrap();
+    /// ```
+    pub fn execute_timeout<J: Job<'static>>(&self, job: J, timeout: Duration) -> std::result::Result<(), SubmitError<J>> {
+        self.inner.submit(job, Some(Instant::now() + timeout))
+    }
+
+    /// Like [try_execute](Self::try_execute), but never blocks.
+    ///
+    /// # Errors
+    /// - [PoolError::QueueFull] if the pool can't take more jobs right now
+    /// - [PoolError::ShutDown] if all the workers have exited
+    pub fn try_execute_now<J: Job<'static>>(&self, job: J) -> std::result::Result<(), SubmitError<J>> {
+        self.inner.submit(job, Some(Instant::now()))
+    }
+
+    /// Executes the given job inside this pool, and returns
+    /// a [JobHandle] to get it's result.
+    ///
+    /// # Example
+    /// ```
+    /// use job_pool::ThreadPool;
+    ///
+    /// let pool = ThreadPool::default();
+    /// let handle = pool.spawn(|| 2 + 2);
+    /// assert_eq!(handle.join().unwrap(), 4);
+    ///
+    /// let handle = pool.spawn(|| panic!("Oops"));
+    /// assert!(handle.join().is_err());
+    /// ```
+    pub fn spawn<T, F>(&self, f: F) -> JobHandle<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let packet = Arc::new(Packet::new());
+        let completion = Completion::new(Arc::clone(&packet));
+        self.execute(move || completion.run(f));
+        JobHandle::new(packet)
+    }
+
+    /// Spawns a future in this pool, and returns a [JobHandle] to get it's output.
+    ///
+    /// The future is polled by the pool's workers. Every time it's woken,
+    /// it's sent back to the pool to be polled again.
+    ///
+    /// The future counts as a single job for [pending_jobs](Self::pending_jobs),
+    /// [join](Self::join) and [max_jobs](PoolConfig::max_jobs), from the moment
+    /// it's spawned until it completes. Each poll runs between the
+    /// [before_job](crate::Hooks::before_job) and [after_job](crate::Hooks::after_job) hooks.
+    ///
+    /// If the pool is dropped before the future completes, the
+    /// handle returns a [PoolError::ShutDown].
+    ///
+    /// # Example
+    /// ```
+    /// use job_pool::ThreadPool;
+    ///
+    /// let pool = ThreadPool::default();
+    /// let a = pool.spawn(|| 2);
+    /// let handle = pool.spawn_future(async move {
+    ///     let a = a.await.unwrap();
+    ///     a * 2
+    /// });
+    /// assert_eq!(job_pool::block_on(handle).unwrap(), 4);
+    /// ```
+    pub fn spawn_future<F>(&self, fut: F) -> JobHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let packet = Arc::new(Packet::new());
+        let completion = Completion::new(Arc::clone(&packet));
+        let fut = async move {
+            let result = CatchUnwind(Box::pin(fut)).await;
+            completion.complete(result);
         };
-        self.sender.send(msg).unwrap();
+
+        self.inner.job_count.inc(self.inner.max_jobs);
+        self.inner.shared.metrics.submitted.fetch_add(1, Ordering::Relaxed);
+        let task = Task::new(Box::pin(fut), Arc::downgrade(&self.inner), self.inner.job_count.clone());
+        task.schedule();
+        JobHandle::new(packet)
+    }
+
+    fn timer(&self) -> &Timer {
+        self.timer.get_or_init(|| Timer::new(Arc::clone(&self.inner)))
+    }
+
+    /// Executes the given job after `delay`
+    ///
+    /// Jobs are scheduled by a timer thread, which is started the first time
+    /// a job is scheduled. The job only counts towards [pending_jobs](Self::pending_jobs)
+    /// and [join](Self::join) once it's due. Jobs that are not due when the pool
+    /// drops are discarded.
+    ///
+    /// Due jobs are sent to the pool even if it's at it's [max_jobs](PoolConfig::max_jobs)
+    /// or [incoming_buf_size](PoolConfig::incoming_buf_size), so the timer never blocks.
+    ///
+    /// If `delay` is too big to be represented as an [Instant] (like [Duration::MAX]),
+    /// the job is never due, so it's discarded right away.
+    ///
+    /// # Example
+    /// ```
+    /// use job_pool::ThreadPool;
+    /// use std::time::Duration;
+    ///
+    /// let pool = ThreadPool::default();
+    /// pool.execute_after(Duration::from_millis(10), || println!("Hello from the future"));
+    /// ```
+    pub fn execute_after(&self, delay: Duration, job: impl Job<'static>) {
+        if let Some(at) = Instant::now().checked_add(delay) {
+            self.execute_at(at, job);
+        }
+    }
+
+    /// Executes the given job at the given [Instant]
+    ///
+    /// See [execute_after](Self::execute_after)
+    pub fn execute_at(&self, at: Instant, job: impl Job<'static>) {
+        self.timer().schedule(at, Box::new(job));
+    }
+
+    /// Executes the given job every `period`, starting one period from now.
+    ///
+    /// Missed ticks are skipped. See [execute_every_with](Self::execute_every_with)
+    ///
+    /// # Errors
+    /// [PoolError::InvalidPeriod] if `period` is zero
+    ///
+    /// # Example
+    /// ```
+    /// use job_pool::ThreadPool;
+    /// use std::time::Duration;
+    ///
+    /// let pool = ThreadPool::default();
+    /// let handle = pool.execute_every(Duration::from_millis(10), || println!("Tick")).unwrap();
+    /// std::thread::sleep(Duration::from_millis(50));
+    /// handle.cancel();
+    /// ```
+    pub fn execute_every<F>(&self, period: Duration, job: F) -> Result<ScheduleHandle>
+    where
+        F: FnMut() + Send + 'static
+    {
+        self.execute_every_with(period, MissedTickPolicy::Skip, job)
+    }
+
+    /// Executes the given job every `period`, with the given [MissedTickPolicy]
+    ///
+    /// Ticks of the same job never run concurrently.
+    ///
+    /// # Errors
+    /// [PoolError::InvalidPeriod] if `period` is zero
+    pub fn execute_every_with<F>(&self, period: Duration, policy: MissedTickPolicy, job: F) -> Result<ScheduleHandle>
+    where
+        F: FnMut() + Send + 'static
+    {
+        self.timer().schedule_periodic(period, policy, Box::new(job))
     }
 
     /// Creates a new [Scope] to spawn jobs.
@@ -120,6 +487,13 @@ impl ThreadPool {
     /// All the jobs spawned via [Scope::execute], will be joined
     /// when the scope drops.
     ///
+    /// The scope's jobs can be cancelled with it's [CancellationToken].
+    /// See [Scope::cancellation_token]
+    ///
+    /// If any job panics, the first panic is propagated to the
+    /// caller once all the jobs have finished. Use [try_scope](Self::try_scope)
+    /// to get all the panics as an error instead.
+    ///
     /// # Example
     /// ```
     /// use job_pool::ThreadPool;
@@ -145,28 +519,458 @@ impl ThreadPool {
         F: FnOnce(&Scope<'scope, 'pool>) -> R,
         'pool: 'scope
     {
-        let scope = Scope::new(self);
-        f(&scope)
+        Scope::new(self, Priority::Normal, CancellationToken::new())
+            .run(f)
+            .unwrap_or_else(|err| err.resume())
+    }
+
+    /// Like [scope](Self::scope), but returns the panics of all the jobs
+    /// that panicked, instead of propagating them.
+    ///
+    /// # Errors
+    /// [PoolError::ScopePanicked] if any job panicked
+    ///
+    /// # Example
+    /// ```
+    /// use job_pool::{PoolError, ThreadPool};
+    ///
+    /// let pool = ThreadPool::default();
+    ///
+    /// let res = pool.try_scope(|scope| {
+    ///     scope.execute(|| panic!("Oops"));
+    ///     scope.execute(|| println!("I'm fine"));
+    /// });
+    /// let Err(PoolError::ScopePanicked(err)) = res else { unreachable!() };
+    /// assert_eq!(err.panics().len(), 1);
+    /// ```
+    pub fn try_scope<'scope, 'pool, F, R>(&'pool self, f: F) -> Result<R>
+    where
+        F: FnOnce(&Scope<'scope, 'pool>) -> R,
+        'pool: 'scope
+    {
+        Scope::new(self, Priority::Normal, CancellationToken::new())
+            .run(f)
+            .map_err(PoolError::ScopePanicked)
     }
 
     /// Waits for all the jobs in the pool to finish
     pub fn join(&self) {
-        self.job_count.join();
+        self.inner.job_count.join();
+    }
+
+    /// Waits for all the jobs in the pool to finish, for at most `timeout`.
+    ///
+    /// # Errors
+    /// [TimedOut](PoolError::TimedOut) if there are still
+    /// pending jobs after `timeout`.
+    pub fn join_timeout(&self, timeout: Duration) -> Result<()> {
+        if self.inner.job_count.join_timeout(timeout) {
+            Ok(())
+        } else {
+            Err(PoolError::TimedOut)
+        }
+    }
+
+    /// Runs `a` and `b` in parallel, and returns both results.
+    ///
+    /// `a` runs in the current thread, while `b` is offered to the other
+    /// workers. If none of them has taken `b` by the time `a` finishes,
+    /// the current thread runs it too. If the pool's queue is full, `b`
+    /// isn't offered at all, and just runs after `a`.
+    ///
+    /// While waiting for `b`, a worker of this pool keeps running other
+    /// jobs. So this can be called recursively from inside a job
+    /// without deadlocking the pool.
+    ///
+    /// # Panics
+    /// If `a` or `b` panic, the panic is propagated once both are done.
+    ///
+    /// # Example
+    /// ```
+    /// use job_pool::ThreadPool;
+    ///
+    /// fn sum(pool: &ThreadPool, nums: &[u64]) -> u64 {
+    ///     if nums.len() <= 1024 {
+    ///         return nums.iter().sum();
+    ///     }
+    ///     let (l, r) = nums.split_at(nums.len() / 2);
+    ///     let (l, r) = pool.join2(|| sum(pool, l), || sum(pool, r));
+    ///     l + r
+    /// }
+    ///
+    /// let pool = ThreadPool::default();
+    /// let nums: Vec<u64> = (0..100_000).collect();
+    /// assert_eq!(sum(&pool, &nums), nums.iter().sum());
+    /// ```
+    pub fn join2<A, B, RA, RB>(&self, a: A, b: B) -> (RA, RB)
+    where
+        A: FnOnce() -> RA + Send,
+        B: FnOnce() -> RB + Send,
+        RA: Send,
+        RB: Send,
+    {
+        let fork = Arc::new(Fork::new(b));
+        let f = Arc::clone(&fork);
+        let job: Box<dyn Job<'_>> = Box::new(move || f.run());
+        /* SAFETY: We don't return until `b` has been either run or
+         * taken back from the fork. After that, the job only checks
+         * that the fork is empty, so it doesn't touch any borrowed data. */
+        let job: Box<dyn Job<'static>> = unsafe { mem::transmute(job) };
+        /* Don't wait for max_jobs or space in the queue here, the caller
+         * may be the job that has to finish for a slot to be released.
+         * If the queue is full, the job is dropped, and `b` is run below. */
+        self.inner.execute_if_space(job, Priority::Normal);
+
+        let ra = panic::catch_unwind(AssertUnwindSafe(a));
+        let rb = match fork.take() {
+            Some(b) => panic::catch_unwind(AssertUnwindSafe(b)),
+            None => {
+                self.inner.wait_helping(&fork.packet);
+                fork.packet.wait().map_err(|err| match err {
+                    PoolError::JobPanicked(panic) => panic.into_payload(),
+                    err => Box::new(err),
+                })
+            }
+        };
+        match (ra, rb) {
+            (Ok(ra), Ok(rb)) => (ra, rb),
+            (Err(payload), _) | (_, Err(payload)) => panic::resume_unwind(payload),
+        }
+    }
+
+    /// Returns a [parallel iterator](crate::par) over `slice`
+    ///
+    /// # Example
+    /// ```
+    /// use job_pool::ThreadPool;
+    ///
+    /// let pool = ThreadPool::default();
+    /// let words = ["a", "bb", "ccc"];
+    /// let lens: Vec<usize> = pool.par_iter(&words).map(|w| w.len()).collect();
+    /// assert_eq!(lens, [1, 2, 3]);
+    /// ```
+    pub fn par_iter<'data, T: Sync>(&self, slice: &'data [T]) -> par::Iter<'_, 'data, T> {
+        par::Iter::new(self, slice)
+    }
+
+    /// Applies `f` to each item of `iter` in this pool, and returns an
+    /// iterator over the results, in the same order as the items.
+    ///
+    /// Items are taken from `iter` as the results are consumed, so at most
+    /// `max_in_flight` jobs are queued or running at any time. Submitting
+    /// the jobs respects the [max_jobs](PoolConfig::max_jobs) of the pool.
+    ///
+    /// If `f` panics, a [PoolError::JobPanicked] is yielded in place
+    /// of it's result.
+    ///
+    /// # Example
+    /// ```
+    /// use job_pool::ThreadPool;
+    ///
+    /// let pool = ThreadPool::default();
+    /// let lines = ["a", "bb", "ccc"].into_iter();
+    /// let lens: Vec<usize> = pool.map_ordered(lines, 2, |l| l.len())
+    ///                            .map(Result::unwrap)
+    ///                            .collect();
+    /// assert_eq!(lens, [1, 2, 3]);
+    /// ```
+    pub fn map_ordered<I, F, T>(&self, iter: I, max_in_flight: usize, f: F) -> MapOrdered<'_, I::IntoIter, F, T>
+    where
+        I: IntoIterator,
+        I::Item: Send + 'static,
+        F: Fn(I::Item) -> T + Send + Sync + 'static,
+        T: Send + 'static,
+    {
+        MapOrdered::new(self, iter.into_iter(), max_in_flight, f)
+    }
+
+    /// Like [map_ordered](Self::map_ordered), but the results are
+    /// yielded as the jobs finish, instead of in input order.
+    pub fn map_unordered<I, F, T>(&self, iter: I, max_in_flight: usize, f: F) -> MapUnordered<'_, I::IntoIter, F, T>
+    where
+        I: IntoIterator,
+        I::Item: Send + 'static,
+        F: Fn(I::Item) -> T + Send + Sync + 'static,
+        T: Send + 'static,
+    {
+        MapUnordered::new(self, iter.into_iter(), max_in_flight, f)
+    }
+
+    /// Executes the given job inside this pool, without blocking.
+    ///
+    /// If the pool has reached it's [max_jobs](PoolConfig::max_jobs), the
+    /// returned future yields until a slot is free, instead of blocking
+    /// the current thread like [execute](Self::execute) does. It doesn't
+    /// depend on any specific async runtime.
+    ///
+    /// Note that if the pool has an [incoming_buf_size](PoolConfig::incoming_buf_size),
+    /// sending the job may still block while the buffer is full.
+    ///
+    /// # Example
+    /// ```
+    /// use job_pool::ThreadPool;
+    ///
+    /// # async fn example() {
+    /// let pool = ThreadPool::default();
+    /// pool.execute_async(|| println!("Hello")).await;
+    /// pool.join_async().await;
+    /// # }
+    /// ```
+    pub fn execute_async<J: Job<'static>>(&self, job: J) -> ExecuteAsync<'_, J> {
+        ExecuteAsync::new(self, job)
+    }
+
+    /// Waits for all the jobs in the pool to finish, without blocking.
+    ///
+    /// Async version of [join](Self::join)
+    pub fn join_async(&self) -> JoinAsync<'_> {
+        JoinAsync::new(self)
+    }
+
+    pub(crate) fn poll_reserve(&self, cx: &mut Context<'_>) -> Poll<()> {
+        self.inner.job_count.poll_inc(cx, self.inner.max_jobs)
+    }
+
+    pub(crate) fn execute_reserved(&self, job: Box<dyn Job<'static>>) {
+        self.inner.execute_reserved(job, Priority::Normal, None);
+    }
+
+    pub(crate) fn poll_join(&self, cx: &mut Context<'_>) -> Poll<()> {
+        self.inner.job_count.poll_join(cx)
+    }
+}
+
+impl PoolInner {
+    /// Spawns a new worker, if there are more queued messages than
+    /// idle workers and the pool can still grow.
+    fn grow_if_busy(&self) {
+        if self.sender.len() > self.shared.idle.load(Ordering::Acquire) && self.shared.try_grow() {
+            /* On error, the queued jobs will be run by the current workers */
+            self.spawn_worker();
+        }
+    }
+
+    /// Spawns a worker in a slot already reserved with [Shared::try_grow].
+    /// Returns false, and releases the slot, if it couldn't be spawned.
+    fn spawn_worker(&self) -> bool {
+        let mut workers = self.workers.lock().unwrap();
+        if self.shared.closed.load(Ordering::Acquire) {
+            /* The pool was dropped, and it's workers already taken */
+            self.shared.active.fetch_sub(1, Ordering::AcqRel);
+            return false
+        }
+        reap_finished(&mut workers);
+        match Worker::new(self.receiver.clone(), Arc::clone(&self.shared)) {
+            Ok(worker) => {
+                workers.push(worker);
+                true
+            }
+            Err(_) => {
+                self.shared.active.fetch_sub(1, Ordering::AcqRel);
+                false
+            }
+        }
+    }
+
+    /// Returns true if there's a worker to run the jobs.
+    ///
+    /// All the workers of an elastic pool may have retired.
+    /// In that case, a new one is spawned.
+    fn has_workers(&self) -> bool {
+        self.shared.alive.load(Ordering::Acquire) > 0
+            || (self.shared.try_grow() && self.spawn_worker())
+    }
+
+    /// Sends a job, waiting until `deadline` at most for the pool to have
+    /// space for it. If it doesn't, the job is given back.
+    fn submit<J: Job<'static>>(&self, job: J, deadline: Option<Instant>) -> std::result::Result<(), SubmitError<J>> {
+        /* A deadline that already passed means not to block at all */
+        let full = match deadline {
+            Some(deadline) if deadline <= Instant::now() => PoolError::QueueFull,
+            _ => PoolError::TimedOut,
+        };
+        if !self.has_workers() {
+            return Err(SubmitError::new(job, PoolError::ShutDown))
+        }
+        match deadline {
+            Some(deadline) => if !self.job_count.inc_until(self.max_jobs, deadline) {
+                return Err(SubmitError::new(job, full))
+            }
+            None => self.job_count.inc(self.max_jobs),
+        }
+        let permit = match self.sender.reserve(deadline) {
+            Ok(permit) => permit,
+            Err(err) => {
+                self.job_count.dec();
+                let error = match err {
+                    TrySendError::Full(()) => full,
+                    TrySendError::Disconnected(()) => PoolError::ShutDown,
+                };
+                return Err(SubmitError::new(job, error))
+            }
+        };
+        let msg = self.job_message(Box::new(job), None, None);
+        permit.send(msg, Priority::Normal);
+        self.grow_if_busy();
+        Ok(())
+    }
+
+    /// Sends a message, or gives it back if the pool was dropped
+    fn send(&self, msg: Message, priority: Priority) -> std::result::Result<(), Message> {
+        self.sender.send_with_priority(msg, priority).map_err(|SendError(msg)| msg)?;
+        self.grow_if_busy();
+        Ok(())
+    }
+
+    /// Waits for `packet` to be set. If the current thread is a worker of
+    /// this pool, it runs other jobs in the meantime.
+    fn wait_helping<T>(&self, packet: &Packet<T>) {
+        if !self.receiver.is_local_thread() {
+            return
+        }
+        while !packet.is_set() {
+            match self.receiver.try_recv_local() {
+                Some(Message::Shutdown) => {
+                    /* Give it back, and wait without running anything else */
+                    self.sender.send_control(Message::Shutdown);
+                    return
+                }
+                Some(msg) => drop(worker::run_message(msg, &self.shared, Instant::now())),
+                None => { packet.wait_set_timeout(Duration::from_millis(1)); }
+            }
+        }
+    }
+
+    /// Sends a task to be polled. Returns false if the pool was dropped.
+    pub fn send_task(&self, task: Arc<Task>) -> bool {
+        /* Tasks are often woken from a worker, which would deadlock
+         * waiting for space in it's own queue. The task already holds
+         * it's slot in the job counter, so the queue can't grow unbounded. */
+        match self.sender.force_send_with_priority(Message::Task(task), Priority::Normal) {
+            Ok(()) => {
+                self.grow_if_busy();
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    pub fn execute(&self, job: Box<dyn Job<'static>>, priority: Priority, token: Option<CancellationToken>) {
+        self.job_count.inc(self.max_jobs);
+        self.execute_reserved(job, priority, token);
+    }
+
+    /// Sends a job, for which a slot in the job counter has already been reserved
+    pub fn execute_reserved(&self, job: Box<dyn Job<'static>>, priority: Priority, token: Option<CancellationToken>) {
+        let msg = self.job_message(job, None, token);
+        self.send_job(msg, priority);
+    }
+
+    /// Sends a job if there's space in the queue right now, without
+    /// waiting for [max_jobs](PoolConfig::max_jobs) either.
+    /// Returns false if the job was dropped instead.
+    pub fn execute_if_space(&self, job: Box<dyn Job<'static>>, priority: Priority) -> bool {
+        let Ok(permit) = self.sender.reserve(Some(Instant::now())) else { return false };
+        self.job_count.inc(None);
+        let msg = self.job_message(job, None, None);
+        permit.send(msg, priority);
+        self.grow_if_busy();
+        true
+    }
+
+    pub fn execute_inside_scope(
+        &self,
+        job: Box<dyn Job<'static>>,
+        scope: Arc<ScopeState>,
+        priority: Priority,
+        token: CancellationToken,
+    ) {
+        self.job_count.inc(self.max_jobs);
+        scope.inc();
+        let msg = self.job_message(job, Some(scope), Some(token));
+        self.send_job(msg, priority);
+    }
+
+    fn job_message(&self, job: Box<dyn Job<'static>>, scope: Option<Arc<ScopeState>>, token: Option<CancellationToken>) -> Message {
+        self.shared.metrics.submitted.fetch_add(1, Ordering::Relaxed);
+        let guard = JobGuard { global_counter: self.job_count.clone(), scope };
+        let queued = Instant::now();
+        match token {
+            Some(token) => {
+                let job = Cancellable::new(job, guard, token, Arc::clone(&self.shared));
+                Message::Cancellable { job, queued }
+            }
+            None => Message::Job { job, guard, queued },
+        }
+    }
+
+    /// Sends a job message. If the pool was dropped, the
+    /// job is dropped without running, and it's counted as done.
+    fn send_job(&self, msg: Message, priority: Priority) {
+        if let Err(msg) = self.send(msg, priority) {
+            self.discard(msg);
+        }
+    }
+
+    /// Sends a job that's due from the [Timer]. It's sent even
+    /// if the pool is full, so the timer never blocks.
+    pub fn execute_due(&self, job: Box<dyn Job<'static>>) {
+        self.job_count.inc(None);
+        let msg = self.job_message(job, None, None);
+        match self.sender.force_send_with_priority(msg, Priority::Normal) {
+            Ok(()) => self.grow_if_busy(),
+            Err(SendError(msg)) => self.discard(msg),
+        }
+    }
+
+    /// Releases the counters of a job message that couldn't be sent
+    fn discard(&self, msg: Message) {
+        let job = match msg {
+            Message::Job { job, guard, .. } => Some((job, guard)),
+            /* If it was cancelled, it's already been accounted for */
+            Message::Cancellable { job, .. } => job.take_any(),
+            Message::Task(_) | Message::Shutdown => None,
+        };
+        if job.is_some() {
+            self.shared.metrics.submitted.fetch_sub(1, Ordering::Relaxed);
+        }
     }
 }
 
 impl Drop for ThreadPool  {
     fn drop(&mut self) {
-        for _ in 0..self.workers.len() {
-            self.sender.send(Message::Shutdown).unwrap();
+        if let Some(timer) = self.timer.get_mut() {
+            timer.shutdown();
         }
 
-        for worker in &mut self.workers {
+        /* The workers run the jobs that are still queued,
+         * and exit once the queue is empty */
+        let inner = &self.inner;
+        inner.shared.closed.store(true, Ordering::Release);
+        inner.shared.active.store(0, Ordering::Release);
+        inner.sender.close();
+
+        /* Don't hold the lock while joining, a worker may be
+         * waiting for it in spawn_worker */
+        let workers = mem::take(&mut *inner.workers.lock().unwrap());
+        for mut worker in workers {
             worker.shutdown();
         }
     }
 }
 
+/// Joins the workers that have already exited
+fn reap_finished(workers: &mut Vec<Worker>) {
+    workers.retain_mut(|worker| {
+        if worker.is_finished() {
+            worker.shutdown();
+            false
+        } else {
+            true
+        }
+    });
+}
+
 impl Default for ThreadPool {
     fn default() -> Self {
         Self::with_default_config()
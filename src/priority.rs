@@ -0,0 +1,28 @@
+/// Priority of a job
+///
+/// Workers always take the highest priority job available.
+/// See [ThreadPool::execute_with_priority](crate::ThreadPool::execute_with_priority)
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq,Hash)]
+pub enum Priority {
+    High,
+    #[default]
+    Normal,
+    Low,
+}
+
+impl Priority {
+    pub(crate) const LEVELS: usize = 3;
+
+    /// Index of the priority, from highest (0) to lowest
+    pub(crate) const fn index(self) -> usize {
+        self as usize
+    }
+
+    pub(crate) const fn from_index(i: usize) -> Self {
+        match i {
+            0 => Self::High,
+            1 => Self::Normal,
+            _ => Self::Low,
+        }
+    }
+}
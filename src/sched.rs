@@ -0,0 +1,84 @@
+//! Scheduling policy and niceness of the workers
+//!
+//! Only supported on Linux. On other platforms, setting
+//! them makes [ThreadPool::new](crate::ThreadPool::new) fail.
+
+/// Linux scheduling policy of the workers
+///
+/// See sched(7) for the details of each one.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum SchedPolicy {
+    /// SCHED_OTHER, the default time-sharing policy
+    Other,
+    /// SCHED_BATCH, for CPU-bound non-interactive work
+    Batch,
+    /// SCHED_IDLE, only runs when the CPU would be idle otherwise
+    Idle,
+    /// SCHED_FIFO real time policy, with the given priority (1-99)
+    Fifo(u8),
+    /// SCHED_RR real time policy, with the given priority (1-99)
+    RoundRobin(u8),
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    use std::io;
+    use super::SchedPolicy;
+
+    const SCHED_OTHER: i32 = 0;
+    const SCHED_FIFO: i32 = 1;
+    const SCHED_RR: i32 = 2;
+    const SCHED_BATCH: i32 = 3;
+    const SCHED_IDLE: i32 = 5;
+    const PRIO_PROCESS: i32 = 0;
+
+    #[repr(C)]
+    struct SchedParam {
+        sched_priority: i32,
+    }
+
+    unsafe extern "C" {
+        fn sched_setscheduler(pid: i32, policy: i32, param: *const SchedParam) -> i32;
+        fn setpriority(which: i32, who: u32, prio: i32) -> i32;
+    }
+
+    /// Sets the scheduling policy of the current thread
+    pub fn set_policy(policy: SchedPolicy) -> io::Result<()> {
+        let (policy, priority) = match policy {
+            SchedPolicy::Other => (SCHED_OTHER, 0),
+            SchedPolicy::Batch => (SCHED_BATCH, 0),
+            SchedPolicy::Idle => (SCHED_IDLE, 0),
+            SchedPolicy::Fifo(p) => (SCHED_FIFO, p as i32),
+            SchedPolicy::RoundRobin(p) => (SCHED_RR, p as i32),
+        };
+        let param = SchedParam { sched_priority: priority };
+        /* SAFETY: param is a valid sched_param. On Linux, pid 0
+         * refers to the calling thread. */
+        let res = unsafe { sched_setscheduler(0, policy, &param) };
+        if res == 0 { Ok(()) } else { Err(io::Error::last_os_error()) }
+    }
+
+    /// Sets the nice value of the current thread
+    pub fn set_nice(nice: i8) -> io::Result<()> {
+        /* SAFETY: On Linux, who = 0 refers to the calling thread,
+         * since the nice value is a per-thread attribute. */
+        let res = unsafe { setpriority(PRIO_PROCESS, 0, nice as i32) };
+        if res == 0 { Ok(()) } else { Err(io::Error::last_os_error()) }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod sys {
+    use std::io;
+    use super::SchedPolicy;
+
+    pub fn set_policy(_policy: SchedPolicy) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "Scheduling policies are only supported on Linux"))
+    }
+
+    pub fn set_nice(_nice: i8) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "Setting the nice value is only supported on Linux"))
+    }
+}
+
+pub(crate) use sys::{set_nice, set_policy};
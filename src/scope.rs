@@ -1,15 +1,93 @@
+use core::fmt;
 use core::marker::PhantomData;
 use core::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 
+use crate::handle::{Completion, Packet, ScopedJoinHandle};
 use crate::worker::Job;
-use crate::{Counter, ThreadPool};
+use crate::{CancellationToken, Counter, JobPanic, Priority, ThreadPool};
+
+/// State shared between a [Scope] and it's jobs
+pub(crate) struct ScopeState {
+    counter: Counter,
+    panics: Mutex<Vec<JobPanic>>,
+}
+
+impl ScopeState {
+    fn new() -> Self {
+        Self {
+            counter: Counter::new(),
+            panics: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn inc(&self) {
+        self.counter.inc(None);
+    }
+
+    pub fn dec(&self) {
+        self.counter.dec();
+    }
+
+    pub fn panicked(&self, panic: JobPanic) {
+        self.panics.lock().unwrap().push(panic);
+    }
+}
+
+/// Error held by [PoolError::ScopePanicked](crate::PoolError::ScopePanicked),
+/// when [ThreadPool::try_scope] fails
+///
+/// Contains the panics of all the jobs that panicked
+/// inside the scope, in the order they happened.
+pub struct ScopeError {
+    panics: Vec<JobPanic>,
+}
+
+impl ScopeError {
+    /// Returns the panics of the jobs
+    pub fn panics(&self) -> &[JobPanic] {
+        &self.panics
+    }
+
+    /// Consumes self, returning the panics of the jobs
+    pub fn into_panics(self) -> Vec<JobPanic> {
+        self.panics
+    }
+
+    /// Resumes the first panic
+    pub(crate) fn resume(self) -> ! {
+        let first = self.panics.into_iter().next().unwrap();
+        panic::resume_unwind(first.into_payload())
+    }
+}
+
+impl fmt::Debug for ScopeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScopeError")
+         .field("panics", &self.panics)
+         .finish()
+    }
+}
+
+impl fmt::Display for ScopeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} job(s) panicked inside the scope", self.panics.len())
+    }
+}
+
+impl std::error::Error for ScopeError {}
 
 /// A scope to spawn jobs inside a [ThreadPool]
 ///
 /// This struct is created by the [ThreadPool::scope] function
 pub struct Scope<'scope, 'pool: 'scope> {
-    scope_counter: Counter,
+    state: Arc<ScopeState>,
     pool: &'pool ThreadPool,
+    /// Index of the default [Priority] for the jobs
+    priority: AtomicUsize,
+    token: CancellationToken,
 
     /// Invariance over 'scope, to make sure 'scope cannot shrink,
     /// which is necessary for soundness.
@@ -31,26 +109,152 @@ pub struct Scope<'scope, 'pool: 'scope> {
 }
 
 impl<'scope, 'pool> Scope<'scope, 'pool> {
-    pub(super) fn new(pool: &'pool ThreadPool) -> Self {
+    pub(super) fn new(pool: &'pool ThreadPool, priority: Priority, token: CancellationToken) -> Self {
         Self {
-            scope_counter: Counter::new(),
+            state: Arc::new(ScopeState::new()),
             pool,
+            priority: AtomicUsize::new(priority.index()),
+            token,
             _marker_scope: PhantomData,
         }
     }
 
+    /// Returns the [CancellationToken] of this scope
+    ///
+    /// Cancelling it drops all the queued jobs of this scope and
+    /// it's subscopes. Jobs can also clone it to check if they
+    /// should stop early.
+    ///
+    /// # Example
+    /// ```
+    /// use job_pool::ThreadPool;
+    ///
+    /// let pool = ThreadPool::default();
+    /// pool.scope(|scope| {
+    ///     for i in 0..100 {
+    ///         let token = scope.cancellation_token().clone();
+    ///         scope.execute(move || {
+    ///             if token.is_cancelled() { return }
+    ///             if i == 10 { token.cancel() }
+    ///         });
+    ///     }
+    /// });
+    /// ```
+    pub fn cancellation_token(&self) -> &CancellationToken {
+        &self.token
+    }
+
+    /// Returns the default [Priority] for the jobs of this scope
+    pub fn priority(&self) -> Priority {
+        Priority::from_index(self.priority.load(Ordering::Relaxed))
+    }
+
+    /// Sets the default [Priority] for the jobs of this scope.
+    ///
+    /// Subscopes created after this call inherit the priority.
+    ///
+    /// # Example
+    /// ```
+    /// use job_pool::{Priority, ThreadPool};
+    ///
+    /// let pool = ThreadPool::default();
+    /// pool.scope(|scope| {
+    ///     scope.set_priority(Priority::Low);
+    ///     scope.execute(|| println!("I'm a low priority job"));
+    /// });
+    /// ```
+    pub fn set_priority(&self, priority: Priority) {
+        self.priority.store(priority.index(), Ordering::Relaxed);
+    }
+
+    /// Runs `f` with the given scope, and waits for all it's jobs.
+    ///
+    /// If `f` panics, the panic is propagated after the jobs
+    /// finish. If any job panicked, returns a [ScopeError].
+    pub(super) fn run<F, R>(self, f: F) -> Result<R, ScopeError>
+    where
+        F: FnOnce(&Self) -> R,
+    {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| f(&self)));
+        self.state.counter.join();
+        let result = result.unwrap_or_else(|payload| panic::resume_unwind(payload));
+        let panics = mem::take(&mut *self.state.panics.lock().unwrap());
+        if panics.is_empty() {
+            Ok(result)
+        } else {
+            Err(ScopeError { panics })
+        }
+    }
+
     /// Executes a job inside this [Scope].
     pub fn execute(&self, job: impl Job<'scope>) {
+        self.execute_with_priority(self.priority(), job);
+    }
+
+    /// Executes a job inside this [Scope], with the given [Priority]
+    pub fn execute_with_priority(&self, priority: Priority, job: impl Job<'scope>) {
         let job: Box<dyn Job<'scope>> = Box::new(job);
         /* SAFETY: Scope makes sure that all jobs sent through it are
          * finished before droping it. So the jobs won't outlive the
          * 'scope lifetime. */
         let job: Box<dyn Job<'static>> = unsafe { mem::transmute(job) };
-        self.pool.execute_inside_scope(job, self.scope_counter.clone());
+        self.pool.execute_inside_scope(job, Arc::clone(&self.state), priority, self.token.clone());
+    }
+
+    /// Runs `a` and `b` in parallel, and returns both results.
+    ///
+    /// See [ThreadPool::join2](crate::ThreadPool::join2)
+    pub fn join<A, B, RA, RB>(&self, a: A, b: B) -> (RA, RB)
+    where
+        A: FnOnce() -> RA + Send,
+        B: FnOnce() -> RB + Send,
+        RA: Send,
+        RB: Send,
+    {
+        self.pool.join2(a, b)
+    }
+
+    /// Executes a job inside this [Scope], and returns a
+    /// [ScopedJoinHandle] to get it's result.
+    ///
+    /// If the job panics, the panic is returned when joining the
+    /// handle. If the handle is dropped without joining it, the
+    /// panic is propagated by the scope, like the ones of
+    /// [execute](Self::execute).
+    ///
+    /// # Example
+    /// ```
+    /// use job_pool::ThreadPool;
+    ///
+    /// let pool = ThreadPool::default();
+    ///
+    /// let nums = vec![1, 2, 3, 4];
+    /// pool.scope(|scope| {
+    ///     let sum = scope.spawn(|| nums.iter().sum::<i32>());
+    ///     let max = scope.spawn(|| nums.iter().max().copied());
+    ///     assert_eq!(sum.join().unwrap(), 10);
+    ///     assert_eq!(max.join().unwrap(), Some(4));
+    /// });
+    /// ```
+    pub fn spawn<T, F>(&self, f: F) -> ScopedJoinHandle<'scope, T>
+    where
+        T: Send + 'scope,
+        F: FnOnce() -> T + Send + 'scope,
+    {
+        let packet = Arc::new(Packet::for_scope(Arc::clone(&self.state)));
+        let completion = Completion::new(Arc::clone(&packet));
+        self.execute(move || completion.run(f));
+        ScopedJoinHandle::new(packet)
     }
 
     /// Creates a new scope inside `self`.
     ///
+    /// Like [ThreadPool::scope], if any job inside the subscope
+    /// panics, the panic is propagated to the caller.
+    ///
+    /// The subscope's [CancellationToken] is a child of this scope's
+    /// token, so cancelling this scope also cancels the subscope.
+    ///
     /// # Example
     /// ```
     /// use job_pool::ThreadPool;
@@ -71,17 +275,12 @@ impl<'scope, 'pool> Scope<'scope, 'pool> {
         F: FnOnce(&Scope<'new, 'pool>) -> R,
         'scope: 'new
     {
-        let scope = Scope {
-            scope_counter: Counter::new(),
-            pool: self.pool,
-            _marker_scope: PhantomData,
-        };
-        f(&scope)
+        Scope::new(self.pool, self.priority(), self.token.child_token()).run(f).unwrap_or_else(|err| err.resume())
     }
 }
 
 impl Drop for Scope<'_, '_> {
     fn drop(&mut self) {
-        self.scope_counter.join();
+        self.state.counter.join();
     }
 }
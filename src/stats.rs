@@ -0,0 +1,173 @@
+//! Runtime statistics of a [ThreadPool](crate::ThreadPool)
+
+use core::time::Duration;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::OnceLock;
+
+/// Number of buckets of a [Histogram]
+const BUCKETS: usize = 64;
+
+/// Distribution of durations, in log2 buckets
+///
+/// Bucket `i` counts the durations between 2<sup>i</sup> and
+/// 2<sup>i+1</sup> nanoseconds. The first one also counts zero.
+#[derive(Clone,Debug)]
+pub struct Histogram {
+    buckets: [u64; BUCKETS],
+}
+
+impl Histogram {
+    /// Returns the number of durations in each bucket
+    pub fn buckets(&self) -> &[u64] {
+        &self.buckets
+    }
+
+    /// Returns the range of durations counted by the `i`th bucket
+    pub fn bucket_range(i: usize) -> (Duration, Duration) {
+        let start = if i == 0 { 0 } else { 1_u64 << i };
+        let end = 1_u64.checked_shl(i as u32 + 1).unwrap_or(u64::MAX);
+        (Duration::from_nanos(start), Duration::from_nanos(end))
+    }
+
+    /// Returns the total number of durations
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().sum()
+    }
+
+    /// Returns an upper bound of the `q` quantile, with `q` between 0 and 1.
+    ///
+    /// Returns None if the histogram is empty.
+    pub fn quantile(&self, q: f64) -> Option<Duration> {
+        let count = self.count();
+        if count == 0 {
+            return None
+        }
+        let target = ((count as f64 * q.clamp(0.0, 1.0)).ceil() as u64).max(1);
+        let mut seen = 0;
+        self.buckets.iter().position(|&n| {
+            seen += n;
+            seen >= target
+        }).map(|i| Self::bucket_range(i).1)
+    }
+}
+
+/// Lock-free version of [Histogram], updated by the workers
+pub(crate) struct AtomicHistogram([AtomicU64; BUCKETS]);
+
+impl AtomicHistogram {
+    pub fn new() -> Self {
+        Self([const { AtomicU64::new(0) }; BUCKETS])
+    }
+
+    pub fn record(&self, duration: Duration) {
+        let nanos = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+        let i = (u64::BITS - 1 - nanos.max(1).leading_zeros()) as usize;
+        self.0[i].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> Histogram {
+        Histogram {
+            buckets: self.0.each_ref().map(|n| n.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// Counters shared by all the workers of a pool
+pub(crate) struct Metrics {
+    pub submitted: AtomicU64,
+    pub completed: AtomicU64,
+    pub panicked: AtomicU64,
+    pub cancelled: AtomicU64,
+    /// Number of workers running a job
+    pub busy: AtomicUsize,
+    pub queue_wait: AtomicHistogram,
+    pub run_time: AtomicHistogram,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self {
+            submitted: AtomicU64::new(0),
+            completed: AtomicU64::new(0),
+            panicked: AtomicU64::new(0),
+            cancelled: AtomicU64::new(0),
+            busy: AtomicUsize::new(0),
+            queue_wait: AtomicHistogram::new(),
+            run_time: AtomicHistogram::new(),
+        }
+    }
+}
+
+/// Counters of a single worker
+pub(crate) struct WorkerMetrics {
+    pub jobs: AtomicU64,
+    /// Time spent running jobs, in nanoseconds
+    pub busy: AtomicU64,
+    /// CPUs the worker can run on, set when it starts
+    pub affinity: OnceLock<Vec<usize>>,
+}
+
+impl WorkerMetrics {
+    pub fn new() -> Self {
+        Self {
+            jobs: AtomicU64::new(0),
+            busy: AtomicU64::new(0),
+            affinity: OnceLock::new(),
+        }
+    }
+
+    pub fn add_busy(&self, duration: Duration) {
+        let nanos = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+        self.busy.fetch_add(nanos, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> WorkerStats {
+        WorkerStats {
+            jobs: self.jobs.load(Ordering::Relaxed),
+            busy_time: Duration::from_nanos(self.busy.load(Ordering::Relaxed)),
+            affinity: self.affinity.get().cloned(),
+        }
+    }
+}
+
+/// Statistics of a single worker
+#[derive(Clone,Debug)]
+#[non_exhaustive]
+pub struct WorkerStats {
+    /// Number of jobs run by this worker
+    pub jobs: u64,
+    /// Time spent running jobs
+    pub busy_time: Duration,
+    /// CPUs the worker can run on, after applying the pool's
+    /// [Affinity](crate::Affinity). None if it couldn't be read.
+    pub affinity: Option<Vec<usize>>,
+}
+
+/// A snapshot of the statistics of a [ThreadPool](crate::ThreadPool)
+///
+/// Returned by [ThreadPool::stats](crate::ThreadPool::stats). The
+/// counters are read one by one while the pool is running, so they
+/// may be slightly out of sync with each other.
+#[derive(Clone,Debug)]
+#[non_exhaustive]
+pub struct PoolStats {
+    /// Number of jobs and futures sent to the pool
+    pub submitted: u64,
+    /// Number of jobs and futures that finished running, including the panicked ones
+    pub completed: u64,
+    /// Number of jobs that panicked
+    pub panicked: u64,
+    /// Number of jobs dropped without running, because they were cancelled
+    pub cancelled: u64,
+    /// Number of jobs and futures waiting in the queue
+    pub queue_depth: usize,
+    /// Number of workers running a job
+    pub busy_workers: usize,
+    /// Statistics of each running worker
+    pub workers: Vec<WorkerStats>,
+    /// Time the jobs spent in the queue before a worker took them
+    pub queue_wait: Histogram,
+    /// Time the jobs took to run. For futures, the total
+    /// time spent polling them.
+    pub run_time: Histogram,
+}
@@ -0,0 +1,303 @@
+use core::cmp::Ordering as CmpOrdering;
+use core::time::Duration;
+use std::collections::BinaryHeap;
+use std::cmp::Reverse;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, PoisonError, Weak};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+use crate::pool::PoolInner;
+use crate::worker::Job;
+use crate::{PoolError, Result};
+
+/// What to do when a periodic job misses one or more ticks
+///
+/// A tick is missed when the job is still running from the
+/// previous tick, or when the pool can't keep up.
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub enum MissedTickPolicy {
+    /// Skip the missed ticks, and wait for the next one
+    #[default]
+    Skip,
+    /// Run all the missed ticks as soon as possible, one after the other
+    CatchUp,
+}
+
+/// Returns `period` in nanoseconds, or an error if it's zero
+fn period_nanos(period: Duration) -> Result<u64> {
+    match u64::try_from(period.as_nanos()) {
+        Ok(0) => Err(PoolError::InvalidPeriod),
+        Ok(nanos) => Ok(nanos),
+        Err(_) => Ok(u64::MAX),
+    }
+}
+
+/// State of a periodic job
+struct Periodic {
+    job: Mutex<Box<dyn FnMut() + Send>>,
+    /// Period, in nanoseconds. Never zero.
+    period: AtomicU64,
+    policy: MissedTickPolicy,
+    cancelled: AtomicBool,
+    /// Number of ticks sent to the pool that haven't finished.
+    /// They are run one after the other by a single job.
+    pending: AtomicU64,
+    /// Incremented when the period changes. The entries of
+    /// older generations are ignored.
+    generation: AtomicU64,
+}
+
+impl Periodic {
+    fn period(&self) -> Duration {
+        Duration::from_nanos(self.period.load(Ordering::Relaxed))
+    }
+
+    /// Returns the instant of the tick following `last`
+    fn next_tick(&self, last: Instant, now: Instant) -> Instant {
+        let period = self.period();
+        let next = last + period;
+        match self.policy {
+            MissedTickPolicy::CatchUp => next,
+            MissedTickPolicy::Skip if next > now => next,
+            MissedTickPolicy::Skip => {
+                /* The first tick after now, keeping the same phase */
+                let late = (now - last).as_nanos() % period.as_nanos();
+                now + (period - Duration::from_nanos(late as u64))
+            }
+        }
+    }
+
+    /// Adds a tick. Returns true if a job must be sent
+    /// to the pool to run it.
+    fn add_tick(&self) -> bool {
+        match self.policy {
+            MissedTickPolicy::Skip => {
+                self.pending.compare_exchange(0, 1, Ordering::AcqRel, Ordering::Acquire).is_ok()
+            }
+            MissedTickPolicy::CatchUp => self.pending.fetch_add(1, Ordering::AcqRel) == 0,
+        }
+    }
+
+    /// Runs the pending ticks
+    fn run(&self) {
+        /* Drop the pending ticks if the job panics, so the next one is sent */
+        struct Reset<'a>(&'a AtomicU64);
+        impl Drop for Reset<'_> {
+            fn drop(&mut self) {
+                if thread::panicking() {
+                    self.0.store(0, Ordering::Release);
+                }
+            }
+        }
+        let _reset = Reset(&self.pending);
+        let mut job = self.job.lock().unwrap_or_else(PoisonError::into_inner);
+        loop {
+            if !self.cancelled.load(Ordering::Acquire) {
+                job();
+            }
+            if self.pending.fetch_sub(1, Ordering::AcqRel) == 1 {
+                break
+            }
+        }
+    }
+}
+
+/// A handle to a periodic job, created by [ThreadPool::execute_every](crate::ThreadPool::execute_every)
+///
+/// Dropping the handle doesn't cancel the job.
+#[derive(Clone)]
+pub struct ScheduleHandle {
+    periodic: Arc<Periodic>,
+    timer: Weak<TimerState>,
+}
+
+impl ScheduleHandle {
+    /// Cancels the job. It won't be run again, although a tick
+    /// that's already running or queued in the pool will finish.
+    pub fn cancel(&self) {
+        self.periodic.cancelled.store(true, Ordering::Release);
+    }
+
+    /// Returns true if the job has been cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.periodic.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Returns the period of the job
+    pub fn period(&self) -> Duration {
+        self.periodic.period()
+    }
+
+    /// Changes the period of the job.
+    ///
+    /// The tick that was scheduled is discarded, and the
+    /// next one is scheduled one new period from now.
+    ///
+    /// # Errors
+    /// [PoolError::InvalidPeriod] if `period` is zero
+    pub fn set_period(&self, period: Duration) -> Result<()> {
+        let periodic = &self.periodic;
+        periodic.period.store(period_nanos(period)?, Ordering::Relaxed);
+        let generation = periodic.generation.fetch_add(1, Ordering::AcqRel) + 1;
+        if let Some(timer) = self.timer.upgrade() {
+            timer.push(Instant::now() + period, Task::Periodic(Arc::clone(periodic), generation));
+        }
+        Ok(())
+    }
+}
+
+enum Task {
+    Once(Box<dyn Job<'static>>),
+    /// A tick of a periodic job, and the generation it belongs to
+    Periodic(Arc<Periodic>, u64),
+}
+
+struct Entry {
+    at: Instant,
+    /// Keeps the entries with the same instant in FIFO order
+    seq: u64,
+    task: Task,
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == CmpOrdering::Equal
+    }
+}
+
+impl Eq for Entry {}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        (self.at, self.seq).cmp(&(other.at, other.seq))
+    }
+}
+
+struct Queue {
+    entries: BinaryHeap<Reverse<Entry>>,
+    next_seq: u64,
+    shutdown: bool,
+}
+
+struct TimerState {
+    queue: Mutex<Queue>,
+    cvar: Condvar,
+}
+
+impl TimerState {
+    fn push(&self, at: Instant, task: Task) {
+        let mut queue = self.queue.lock().unwrap();
+        let seq = queue.next_seq;
+        queue.next_seq += 1;
+        queue.entries.push(Reverse(Entry { at, seq, task }));
+        self.cvar.notify_one();
+    }
+}
+
+/// Timer thread that sends the scheduled jobs to the pool
+/// when they are due.
+pub(crate) struct Timer {
+    state: Arc<TimerState>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Timer {
+    pub fn new(pool: Arc<PoolInner>) -> Self {
+        let state = Arc::new(TimerState {
+            queue: Mutex::new(Queue {
+                entries: BinaryHeap::new(),
+                next_seq: 0,
+                shutdown: false,
+            }),
+            cvar: Condvar::new(),
+        });
+        let st = Arc::clone(&state);
+        let thread = thread::spawn(move || run(&st, &pool));
+        Self { state, thread: Some(thread) }
+    }
+
+    pub fn schedule(&self, at: Instant, job: Box<dyn Job<'static>>) {
+        self.state.push(at, Task::Once(job));
+    }
+
+    pub fn schedule_periodic(
+        &self,
+        period: Duration,
+        policy: MissedTickPolicy,
+        job: Box<dyn FnMut() + Send>,
+    ) -> Result<ScheduleHandle> {
+        let periodic = Arc::new(Periodic {
+            job: Mutex::new(job),
+            period: AtomicU64::new(period_nanos(period)?),
+            policy,
+            cancelled: AtomicBool::new(false),
+            pending: AtomicU64::new(0),
+            generation: AtomicU64::new(0),
+        });
+        self.state.push(Instant::now() + period, Task::Periodic(Arc::clone(&periodic), 0));
+        Ok(ScheduleHandle {
+            periodic,
+            timer: Arc::downgrade(&self.state),
+        })
+    }
+
+    /// Stops the timer thread. Jobs that are not due yet are discarded.
+    pub fn shutdown(&mut self) {
+        self.state.queue.lock().unwrap().shutdown = true;
+        self.state.cvar.notify_one();
+        if let Some(thread) = self.thread.take() {
+            /* A panic of the timer thread was already reported by the
+             * panic hook, and dropping the pool must not panic again */
+            let _ = thread.join();
+        }
+    }
+}
+
+fn run(state: &TimerState, pool: &PoolInner) {
+    let mut queue = state.queue.lock().unwrap();
+    loop {
+        if queue.shutdown {
+            break
+        }
+        let now = Instant::now();
+        let Some(Reverse(next)) = queue.entries.peek() else {
+            queue = state.cvar.wait(queue).unwrap();
+            continue
+        };
+        if next.at > now {
+            let timeout = next.at - now;
+            queue = state.cvar.wait_timeout(queue, timeout).unwrap().0;
+            continue
+        }
+        let Reverse(entry) = queue.entries.pop().unwrap();
+        drop(queue);
+        fire(state, pool, entry, now);
+        queue = state.queue.lock().unwrap();
+    }
+}
+
+fn fire(state: &TimerState, pool: &PoolInner, entry: Entry, now: Instant) {
+    match entry.task {
+        Task::Once(job) => pool.execute_due(job),
+        Task::Periodic(periodic, generation) => {
+            if periodic.cancelled.load(Ordering::Acquire)
+                || periodic.generation.load(Ordering::Acquire) != generation
+            {
+                return
+            }
+            if periodic.add_tick() {
+                let p = Arc::clone(&periodic);
+                pool.execute_due(Box::new(move || p.run()));
+            }
+            let next = periodic.next_tick(entry.at, now);
+            state.push(next, Task::Periodic(periodic, generation));
+        }
+    }
+}
@@ -1,6 +1,18 @@
-use std::thread::{JoinHandle,spawn};
-use crate::channel::ReceiverWrapper;
-use crate::Counter;
+use std::cell::OnceCell;
+use std::panic::{self, AssertUnwindSafe};
+use std::process;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread::{Builder, JoinHandle};
+use std::time::{Duration, Instant};
+use crate::channel::{ReceiverWrapper, RecvError, RecvTimeoutError};
+use std::sync::{mpsc, Arc, Mutex, Weak};
+use crate::affinity::{self, Placement};
+use crate::executor::Task;
+use crate::scope::ScopeState;
+use crate::stats::{Metrics, WorkerMetrics, WorkerStats};
+use crate::cancel::OnCancel;
+use crate::handle;
+use crate::{sched, CancellationToken, Counter, Hooks, JobPanic, PanicPolicy, PoolError, Result, SchedPolicy, ThreadName, ThreadSetting};
 
 /// A message sent to the [Worker]
 pub enum Message {
@@ -8,11 +20,19 @@ pub enum Message {
     Job {
         /// The job to be run
         job: Box<dyn Job<'static>>,
-        /// The global [Counter] of jobs
-        global_counter: Counter,
-        /// The [Counter] of jobs for the [Scope](crate::scope::Scope)
-        scope_counter: Option<Counter>,
+        /// Releases the counters of the job
+        guard: JobGuard,
+        /// When the job was sent
+        queued: Instant,
     },
+    /// A job sent with a [CancellationToken]
+    Cancellable {
+        job: Arc<Cancellable>,
+        /// When the job was sent
+        queued: Instant,
+    },
+    /// A future to be polled
+    Task(Arc<Task>),
     Shutdown,
 }
 
@@ -21,35 +41,375 @@ pub trait Job<'scope>: FnOnce() + Send + 'scope {}
 impl<'scope, T> Job<'scope> for T
 where T: FnOnce() + Send + 'scope {}
 
+/// State shared between the [ThreadPool](crate::ThreadPool) and it's workers
+pub struct Shared {
+    /// Number of workers that haven't been told to shut down
+    pub active: AtomicUsize,
+    /// Number of workers waiting for a job
+    pub idle: AtomicUsize,
+    /// Number of worker threads that are running. Unlike `active`,
+    /// this counts workers that were told to shut down, until they exit.
+    pub alive: AtomicUsize,
+    pub min_workers: usize,
+    pub max_workers: usize,
+    /// Time to wait before retiring an idle worker. None if
+    /// workers never retire on their own.
+    pub keep_alive: Option<Duration>,
+    pub panic_policy: PanicPolicy,
+    pub hooks: Hooks,
+    pub thread_name: Option<ThreadName>,
+    pub stack_size: Option<usize>,
+    pub placement: Placement,
+    pub sched_policy: Option<SchedPolicy>,
+    pub nice: Option<i8>,
+    /// Index of the next spawned worker
+    pub next_index: AtomicUsize,
+    /// Held while resizing the pool, so idle workers don't
+    /// retire in the middle of it
+    pub resize: Mutex<()>,
+    /// Set when the pool is dropped, so no more workers are spawned
+    pub closed: AtomicBool,
+    pub metrics: Metrics,
+}
+
+impl Shared {
+    /// Reserves a slot for a new worker, if there are less than `max_workers`
+    pub fn try_grow(&self) -> bool {
+        self.active.fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+            (n < self.max_workers && !self.closed.load(Ordering::Acquire)).then_some(n + 1)
+        }).is_ok()
+    }
+
+    /// Releases the slot of a worker, if there are more than `min_workers`
+    fn try_shrink(&self) -> bool {
+        let _resize = self.resize.lock().unwrap();
+        self.active.fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+            (n > self.min_workers).then(|| n - 1)
+        }).is_ok()
+    }
+}
+
+thread_local! {
+    /// Metrics of the worker running in the current thread
+    static METRICS: OnceCell<Arc<WorkerMetrics>> = const { OnceCell::new() };
+}
+
 /// Worker for the [ThreadPool](crate::ThreadPool)
-pub struct Worker(Option<JoinHandle<()>>);
+pub struct Worker {
+    thread: Option<JoinHandle<()>>,
+    metrics: Arc<WorkerMetrics>,
+}
 
 impl Worker {
     /// Creates a new [Worker]
+    ///
+    /// Waits until the worker thread has applied it's settings.
+    ///
+    /// # Errors
+    /// If the thread couldn't be spawned, or it failed to apply
+    /// one of it's settings. In that case the thread exits.
     pub fn new(
         receiver: ReceiverWrapper<Message>,
-    ) -> Worker {
-        let thread = spawn(move || loop {
-            let message = receiver.recv();
-
-            match message {
-                Ok(Message::Job { job, global_counter, scope_counter }) => {
-                    job();
-                    global_counter.dec();
-                    if let Some(scope) = scope_counter {
-                        scope.dec();
+        shared: Arc<Shared>,
+    ) -> Result<Worker> {
+        let index = shared.next_index.fetch_add(1, Ordering::Relaxed);
+        let mut builder = Builder::new();
+        if let Some(name) = &shared.thread_name {
+            builder = builder.name(name.name(index));
+        }
+        if let Some(size) = shared.stack_size {
+            builder = builder.stack_size(size);
+        }
+
+        let cpus = shared.placement.cpus(index).map(<[usize]>::to_vec);
+        let metrics = Arc::new(WorkerMetrics::new());
+        let m = Arc::clone(&metrics);
+        let (started_tx, started_rx) = mpsc::sync_channel(1);
+        let thread = builder.spawn(move || {
+            let setup = setup(cpus.as_deref(), &shared);
+            if setup.is_err() {
+                let _ = started_tx.send(setup);
+                return
+            }
+            /* Decrement the count when exiting, even if we panic */
+            struct Alive<'a>(&'a AtomicUsize);
+            impl Drop for Alive<'_> {
+                fn drop(&mut self) {
+                    self.0.fetch_sub(1, Ordering::AcqRel);
+                }
+            }
+            shared.alive.fetch_add(1, Ordering::AcqRel);
+            let _alive = Alive(&shared.alive);
+            let _ = started_tx.send(setup);
+            if let Ok(mask) = affinity::get_affinity() {
+                let _ = m.affinity.set(mask);
+            }
+            METRICS.with(|cell| cell.set(Arc::clone(&m)).ok());
+            if let Some(hook) = &shared.hooks.on_thread_start {
+                call_hook(|| hook());
+            }
+            /* Call on_thread_stop when exiting, even if we panic */
+            struct Stop<'a>(&'a Hooks);
+            impl Drop for Stop<'_> {
+                fn drop(&mut self) {
+                    if let Some(hook) = &self.0.on_thread_stop {
+                        call_hook(|| hook());
                     }
                 }
-                Ok(Message::Shutdown) => break,
-                Err(err) => panic!("Receive error: {err}"),
             }
-        });
-        Worker(Some(thread))
+            let _stop = Stop(&shared.hooks);
+            run(&receiver, &shared, &m);
+        }).map_err(PoolError::Spawn)?;
+
+        let mut worker = Worker { thread: Some(thread), metrics };
+        match started_rx.recv() {
+            Ok(Ok(())) => Ok(worker),
+            Ok(Err(err)) => {
+                worker.shutdown();
+                Err(err)
+            }
+            /* The thread panicked while applying it's settings */
+            Err(_) => {
+                let err = worker.thread.take().unwrap().join().unwrap_err();
+                panic::resume_unwind(err)
+            }
+        }
+    }
+    /// Returns the statistics of this [Worker]
+    pub fn stats(&self) -> WorkerStats {
+        self.metrics.snapshot()
+    }
+    /// Returns true if the [Worker] thread has exited
+    pub fn is_finished(&self) -> bool {
+        self.thread.as_ref().is_none_or(JoinHandle::is_finished)
     }
     /// Shuts down the [Worker]
     pub fn shutdown(&mut self) {
-        if let Some(thread) = self.0.take() {
-            thread.join().unwrap();
+        if let Some(thread) = self.thread.take() {
+            /* If the thread panicked, it was already reported */
+            let _ = thread.join();
         }
     }
 }
+
+/// Applies the settings of the current worker thread
+fn setup(cpus: Option<&[usize]>, shared: &Shared) -> Result<()> {
+    let fail = |setting| move |source| PoolError::ThreadSetup { setting, source };
+    if let Some(cpus) = cpus {
+        affinity::set_affinity(cpus).map_err(fail(ThreadSetting::Affinity))?;
+    }
+    if let Some(policy) = shared.sched_policy {
+        sched::set_policy(policy).map_err(fail(ThreadSetting::SchedPolicy))?;
+    }
+    if let Some(nice) = shared.nice {
+        sched::set_nice(nice).map_err(fail(ThreadSetting::Niceness))?;
+    }
+    Ok(())
+}
+
+fn run(receiver: &ReceiverWrapper<Message>, shared: &Shared, metrics: &WorkerMetrics) {
+    loop {
+        shared.idle.fetch_add(1, Ordering::AcqRel);
+        let message = match shared.keep_alive {
+            Some(keep_alive) => match receiver.recv_timeout(keep_alive) {
+                Ok(msg) => Ok(msg),
+                Err(RecvTimeoutError::Timeout) => {
+                    shared.idle.fetch_sub(1, Ordering::AcqRel);
+                    /* If a job was sent in the meantime, the sender may have
+                     * seen this worker as idle, so it must stay to run it */
+                    if shared.try_shrink() && (receiver.is_empty() || !shared.try_grow()) {
+                        break
+                    }
+                    continue
+                }
+                Err(RecvTimeoutError::Disconnected) => Err(RecvError),
+            }
+            None => receiver.recv(),
+        };
+        shared.idle.fetch_sub(1, Ordering::AcqRel);
+
+        match message {
+            Ok(Message::Shutdown) => break,
+            Ok(msg) => {
+                shared.metrics.busy.fetch_add(1, Ordering::Relaxed);
+                let start = Instant::now();
+                /* The panics of the jobs are already handled. This catches the
+                 * ones of dropping their payloads, so the worker never exits
+                 * leaving queued jobs behind. */
+                let guard = panic::catch_unwind(AssertUnwindSafe(|| run_message(msg, shared, start)));
+                metrics.add_busy(start.elapsed());
+                shared.metrics.busy.fetch_sub(1, Ordering::Relaxed);
+                /* Release the job after updating the stats, so
+                 * they're up to date once join returns */
+                drop(guard);
+            }
+            /* The pool was dropped, and there are no jobs left */
+            Err(RecvError) => break,
+        }
+    }
+}
+
+/// Runs a [Job] or [Task] message, received at `start`.
+/// [Shutdown](Message::Shutdown) messages are ignored.
+///
+/// Returns the [JobGuard] of the job once it's done, so the
+/// caller can release it after updating it's own stats.
+#[must_use]
+pub fn run_message(message: Message, shared: &Shared, start: Instant) -> Option<JobGuard> {
+    let metrics = &shared.metrics;
+    match message {
+        Message::Job { job, guard, queued } => {
+            metrics.queue_wait.record(start.saturating_duration_since(queued));
+            Some(run_job(job, guard, shared, start))
+        }
+        Message::Cancellable { job, queued } => {
+            /* If the token was cancelled, the job is already gone */
+            let (job, guard) = job.take()?;
+            metrics.queue_wait.record(start.saturating_duration_since(queued));
+            Some(run_job(job, guard, shared, start))
+        }
+        Message::Task(task) => {
+            let poll_start = before_job(shared, start);
+            let guard = Arc::clone(&task).run();
+            let elapsed = poll_start.elapsed();
+            after_job(shared, elapsed, false);
+            let run_time = task.add_run_time(elapsed);
+            let guard = guard?;
+            metrics.run_time.record(run_time);
+            metrics.completed.fetch_add(1, Ordering::Relaxed);
+            METRICS.with(|m| if let Some(m) = m.get() {
+                m.jobs.fetch_add(1, Ordering::Relaxed);
+            });
+            Some(guard)
+        }
+        Message::Shutdown => None,
+    }
+}
+
+fn run_job(job: Box<dyn Job<'static>>, guard: JobGuard, shared: &Shared, start: Instant) -> JobGuard {
+    let metrics = &shared.metrics;
+    let job_start = before_job(shared, start);
+    let result = panic::catch_unwind(AssertUnwindSafe(job));
+    let elapsed = job_start.elapsed();
+    after_job(shared, elapsed, result.is_err());
+    metrics.run_time.record(elapsed);
+    metrics.completed.fetch_add(1, Ordering::Relaxed);
+    METRICS.with(|m| if let Some(m) = m.get() {
+        m.jobs.fetch_add(1, Ordering::Relaxed);
+    });
+    if let Err(payload) = result {
+        metrics.panicked.fetch_add(1, Ordering::Relaxed);
+        let panic = JobPanic::new(payload);
+        match &guard.scope {
+            Some(scope) => scope.panicked(panic),
+            None => handle_panic(&shared.panic_policy, panic),
+        }
+    }
+    guard
+}
+
+/// Calls the before_job hook, and returns when the job starts
+fn before_job(shared: &Shared, start: Instant) -> Instant {
+    match &shared.hooks.before_job {
+        Some(hook) => {
+            call_hook(|| hook());
+            Instant::now()
+        }
+        None => start,
+    }
+}
+
+fn after_job(shared: &Shared, elapsed: Duration, panicked: bool) {
+    if let Some(hook) = &shared.hooks.after_job {
+        call_hook(|| hook(elapsed, panicked));
+    }
+}
+
+/// Calls a hook. If it panics, the panic hook has already
+/// reported it, so the worker keeps running.
+fn call_hook(hook: impl FnOnce()) {
+    let _ = panic::catch_unwind(AssertUnwindSafe(hook));
+}
+
+/// Decrements the counters of a job when dropped
+pub struct JobGuard {
+    /// The global [Counter] of jobs
+    pub global_counter: Counter,
+    /// The state of the [Scope](crate::scope::Scope) that sent the job
+    pub scope: Option<Arc<ScopeState>>,
+}
+
+impl Drop for JobGuard {
+    fn drop(&mut self) {
+        self.global_counter.dec();
+        if let Some(scope) = &self.scope {
+            scope.dec();
+        }
+    }
+}
+
+/// A job sent with a [CancellationToken]
+///
+/// It's taken by whoever comes first: the worker that runs it, or the
+/// token when it's cancelled. So a cancelled job is dropped, and stops
+/// counting as pending, without waiting for a worker to dequeue it.
+pub struct Cancellable {
+    job: Mutex<Option<(Box<dyn Job<'static>>, JobGuard)>>,
+    token: CancellationToken,
+    shared: Arc<Shared>,
+}
+
+impl Cancellable {
+    pub fn new(job: Box<dyn Job<'static>>, guard: JobGuard, token: CancellationToken, shared: Arc<Shared>) -> Arc<Self> {
+        let cancellable = Arc::new(Self {
+            job: Mutex::new(Some((job, guard))),
+            token: token.clone(),
+            shared,
+        });
+        let listener: Weak<dyn OnCancel> = Arc::downgrade(&cancellable) as _;
+        token.register(listener);
+        cancellable
+    }
+
+    /// Takes the job, without checking the token
+    pub fn take_any(&self) -> Option<(Box<dyn Job<'static>>, JobGuard)> {
+        self.job.lock().unwrap().take()
+    }
+
+    /// Takes the job to run it. Returns None if it was cancelled.
+    fn take(&self) -> Option<(Box<dyn Job<'static>>, JobGuard)> {
+        let (job, guard) = self.take_any()?;
+        /* The token may be cancelled, but not have notified us yet */
+        if self.token.is_cancelled() {
+            drop_cancelled(job, guard, &self.shared);
+            return None
+        }
+        Some((job, guard))
+    }
+}
+
+impl OnCancel for Cancellable {
+    fn cancel(&self) {
+        if let Some((job, guard)) = self.take_any() {
+            drop_cancelled(job, guard, &self.shared);
+        }
+    }
+}
+
+/// Drops a cancelled job, and releases it's counters
+fn drop_cancelled(job: Box<dyn Job<'static>>, guard: JobGuard, shared: &Shared) {
+    /* Dropping the job runs user code, which may panic. The
+     * canceller, or the worker, must keep going anyway. */
+    let _ = panic::catch_unwind(AssertUnwindSafe(|| handle::drop_cancelled_job(job)));
+    shared.metrics.cancelled.fetch_add(1, Ordering::Relaxed);
+    drop(guard);
+}
+
+fn handle_panic(policy: &PanicPolicy, panic: JobPanic) {
+    match policy {
+        /* The panic hook has already printed it */
+        PanicPolicy::Log => {}
+        PanicPolicy::Handler(handler) => call_hook(|| handler(panic)),
+        PanicPolicy::Abort => process::abort(),
+    }
+}
@@ -0,0 +1,54 @@
+#![cfg(target_os = "linux")]
+
+use job_pool::{Affinity, PoolConfig, ThreadPool};
+
+fn pool_with(affinity: Affinity, n: u16) -> ThreadPool {
+    let config = PoolConfig::builder()
+                            .n_workers(n)
+                            .affinity(affinity)
+                            .build();
+    let pool = ThreadPool::new(config).expect("Expected Ok value");
+    /* Make sure all the workers have started */
+    let barrier = std::sync::Barrier::new(n as usize);
+    pool.scope(|scope| {
+        for _ in 0..n {
+            scope.execute(|| { barrier.wait(); });
+        }
+    });
+    pool
+}
+
+/// The CPUs the workers can run on, if they are not pinned
+fn current_mask() -> Vec<usize> {
+    let pool = pool_with(Affinity::None, 1);
+    pool.stats().workers[0].affinity.clone().unwrap()
+}
+
+#[test]
+fn pinned_cores() {
+    let cpu = current_mask()[0];
+    let pool = pool_with(Affinity::Cores(vec![cpu]), 2);
+    let stats = pool.stats();
+    assert_eq!(stats.workers.len(), 2);
+    for worker in stats.workers {
+        assert_eq!(worker.affinity, Some(vec![cpu]));
+    }
+}
+
+#[test]
+fn compact_and_scatter() {
+    let mask = current_mask();
+    for affinity in [Affinity::Compact, Affinity::Scatter] {
+        let pool = pool_with(affinity, 2);
+        for worker in pool.stats().workers {
+            let cpus = worker.affinity.unwrap();
+            assert_eq!(cpus.len(), 1);
+            assert!(mask.contains(&cpus[0]));
+        }
+    }
+}
+
+#[test]
+fn unpinned_reports_mask() {
+    assert!(!current_mask().is_empty());
+}
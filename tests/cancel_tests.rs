@@ -0,0 +1,94 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+
+use job_pool::{CancellationToken, PoolError, ThreadPool};
+
+#[test]
+fn cancelled_jobs_dont_run() {
+    let pool = ThreadPool::with_size(1).expect("Expected Ok value");
+    let (tx, rx) = mpsc::channel::<()>();
+    pool.execute(move || rx.recv().unwrap());
+
+    let token = CancellationToken::new();
+    let count = Arc::new(AtomicUsize::new(0));
+    for _ in 0..10 {
+        let count = Arc::clone(&count);
+        pool.execute_with_token(token.clone(), move || {
+            count.fetch_add(1, Ordering::Relaxed);
+        });
+    }
+    assert_eq!(pool.pending_jobs(), 11);
+    token.cancel();
+    assert_eq!(pool.pending_jobs(), 1);
+    assert_eq!(pool.stats().cancelled, 10);
+    tx.send(()).unwrap();
+    pool.join();
+
+    assert_eq!(count.load(Ordering::Relaxed), 0);
+    assert_eq!(pool.pending_jobs(), 0);
+}
+
+#[test]
+fn cancel_scope() {
+    let pool = ThreadPool::with_size(1).expect("Expected Ok value");
+    let count = AtomicUsize::new(0);
+    let (tx, rx) = mpsc::channel::<()>();
+
+    pool.execute(move || rx.recv().unwrap());
+    pool.scope(|scope| {
+        scope.subscope(|sub| {
+            let handle = sub.spawn(|| count.fetch_add(1, Ordering::Relaxed));
+            for _ in 0..10 {
+                sub.execute(|| { count.fetch_add(1, Ordering::Relaxed); });
+            }
+            scope.cancellation_token().cancel();
+            assert!(sub.cancellation_token().is_cancelled());
+            /* The jobs are dropped right away, without a worker */
+            assert!(matches!(handle.join(), Err(PoolError::Cancelled)));
+            assert_eq!(pool.pending_jobs(), 1);
+            tx.send(()).unwrap();
+        });
+    });
+    assert_eq!(count.load(Ordering::Relaxed), 0);
+}
+
+#[test]
+fn child_token() {
+    let parent = CancellationToken::new();
+    let child = parent.child_token();
+    child.cancel();
+    assert!(!parent.is_cancelled());
+
+    let child = parent.child_token();
+    parent.cancel();
+    assert!(child.is_cancelled());
+}
+
+#[test]
+fn cancel_drops_queued_jobs() {
+    struct PanicOnDrop;
+    impl Drop for PanicOnDrop {
+        fn drop(&mut self) {
+            panic!("Expected panic");
+        }
+    }
+
+    let pool = ThreadPool::with_size(1).expect("Expected Ok value");
+    let (tx, rx) = mpsc::channel::<()>();
+    pool.execute(move || rx.recv().unwrap());
+
+    let parent = CancellationToken::new();
+    let payload = PanicOnDrop;
+    pool.execute_with_token(parent.child_token(), move || drop(payload));
+    pool.execute_with_token(parent.child_token(), || {});
+    assert_eq!(pool.pending_jobs(), 3);
+    parent.cancel();
+    assert_eq!(pool.pending_jobs(), 1);
+
+    pool.execute_with_token(parent.child_token(), || {});
+    assert_eq!(pool.pending_jobs(), 1);
+
+    tx.send(()).unwrap();
+    pool.join();
+    assert_eq!(pool.stats().cancelled, 3);
+}
@@ -0,0 +1,31 @@
+use std::sync::mpsc;
+use std::time::Duration;
+
+use job_pool::{CompletionQueue, PoolError, ThreadPool};
+
+#[test]
+fn completion_order() {
+    let pool = ThreadPool::with_size(2).expect("Expected Ok value");
+    let mut queue = CompletionQueue::new(&pool);
+    let (tx, rx) = mpsc::channel::<()>();
+
+    queue.submit(move || { rx.recv().unwrap(); 1 });
+    queue.submit(|| 2);
+    assert_eq!(queue.next().unwrap().unwrap(), 2);
+    assert!(queue.try_next().is_none());
+    assert!(queue.next_timeout(Duration::from_millis(20)).is_none());
+    assert_eq!(queue.pending(), 1);
+
+    tx.send(()).unwrap();
+    assert_eq!(queue.next_timeout(Duration::from_secs(5)).unwrap().unwrap(), 1);
+    assert!(queue.next().is_none());
+}
+
+#[test]
+fn completion_panic() {
+    let pool = ThreadPool::with_size(2).expect("Expected Ok value");
+    let mut queue = CompletionQueue::<()>::new(&pool);
+    queue.submit(|| panic!("Oops"));
+    let Some(Err(PoolError::JobPanicked(panic))) = queue.next() else { panic!("Expected a JobPanic") };
+    assert_eq!(panic.message(), Some("Oops"));
+}
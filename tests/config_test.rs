@@ -1,4 +1,4 @@
-use job_pool::PoolConfig;
+use job_pool::{PoolConfig, PoolError};
 
 #[test]
 fn blocking_under() {
@@ -7,9 +7,11 @@ fn blocking_under() {
                             .max_jobs(5_u16)
                             .build();
     match config.validate() {
-        Ok(_) => panic!("Expected Err value"),
-        Err(err) => assert_eq!("Max number of jobs (5) is lower \
-                                than the number of workers (10)", err.to_string())
+        Err(PoolError::MaxJobsTooLow { max_jobs, n_workers }) => {
+            assert_eq!(max_jobs, 5);
+            assert_eq!(n_workers, 10);
+        }
+        res => panic!("Expected MaxJobsTooLow, got {res:?}"),
     }
 }
 
@@ -18,8 +20,50 @@ fn size_0() {
     let config = PoolConfig::builder()
                             .n_workers(0_u16)
                             .build();
-    match config.validate() {
-        Ok(_) => panic!("Expected Err value"),
-        Err(err) => assert_eq!("Invalid pool size: 0", err.to_string())
+    assert!(matches!(config.validate(), Err(PoolError::InvalidPoolSize)));
+}
+
+#[test]
+fn min_max_workers() {
+    let config = PoolConfig::builder()
+                            .n_workers(4)
+                            .min_workers(8)
+                            .build();
+    assert!(matches!(config.validate(), Err(PoolError::MinWorkersTooHigh { min_workers: 8, n_workers: 4 })));
+
+    let config = PoolConfig::builder()
+                            .n_workers(4)
+                            .max_workers(2)
+                            .build();
+    assert!(matches!(config.validate(), Err(PoolError::MaxWorkersTooLow { max_workers: 2, n_workers: 4 })));
+
+    let config = PoolConfig::builder()
+                            .n_workers(4)
+                            .min_workers(0)
+                            .max_workers(16)
+                            .build();
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn auto_workers() {
+    let (n, source) = PoolConfig::detect_workers();
+    assert!(n > 0);
+    if cfg!(target_os = "linux") {
+        assert_ne!(source, job_pool::ParallelismSource::Default);
     }
+    let config = PoolConfig::auto();
+    assert_eq!(config.n_workers, n);
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn const_build() {
+    const CONFIG: PoolConfig = PoolConfig::builder()
+                                .n_workers(3)
+                                .max_jobs(6)
+                                .build();
+    assert_eq!(CONFIG.n_workers, 3);
+    assert_eq!(CONFIG.max_jobs, Some(6));
+    assert!(CONFIG.validate().is_ok());
 }
@@ -0,0 +1,190 @@
+use std::future::Future;
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+use std::time::Duration;
+
+use job_pool::{PoolConfig, PoolError, ThreadPool};
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Minimal executor, to avoid depending on an async runtime
+fn block_on<F: Future>(fut: F) -> (F::Output, usize) {
+    let mut fut = pin!(fut);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut polls = 0;
+    loop {
+        polls += 1;
+        if let Poll::Ready(out) = fut.as_mut().poll(&mut cx) {
+            return (out, polls)
+        }
+        thread::park();
+    }
+}
+
+#[test]
+fn await_job_handle() {
+    let pool = ThreadPool::with_size(2).expect("Expected Ok value");
+    let handle = pool.spawn(|| {
+        thread::sleep(Duration::from_millis(50));
+        42
+    });
+    let (res, polls) = block_on(handle);
+    assert_eq!(res.unwrap(), 42);
+    assert!(polls >= 2);
+}
+
+#[test]
+fn await_panicked_job() {
+    let pool = ThreadPool::with_size(2).expect("Expected Ok value");
+    let handle = pool.spawn(|| -> u8 { panic!("Expected panic") });
+    let (res, _) = block_on(handle);
+    let Err(PoolError::JobPanicked(panic)) = res else { panic!("Expected a JobPanic") };
+    assert_eq!(panic.message(), Some("Expected panic"));
+}
+
+#[test]
+fn spawn_future() {
+    let pool = Arc::new(ThreadPool::with_size(4).expect("Expected Ok value"));
+    let handles = (0..100_u64).map(|i| {
+        let p = Arc::clone(&pool);
+        pool.spawn_future(async move {
+            let a = p.spawn(move || i).await.unwrap();
+            let b = p.spawn(move || i * 2).await.unwrap();
+            a + b
+        })
+    }).collect::<Vec<_>>();
+
+    let sum: u64 = handles.into_iter().map(|h| job_pool::block_on(h).unwrap()).sum();
+    assert_eq!(sum, (0..100).map(|i| i * 3).sum());
+    pool.join();
+    assert_eq!(pool.pending_jobs(), 0);
+}
+
+#[test]
+fn join_waits_for_futures() {
+    let pool = ThreadPool::with_size(1).expect("Expected Ok value");
+    let (tx, rx) = std::sync::mpsc::channel();
+    let handle = pool.spawn_future(async move {
+        Sleep::new(Duration::from_millis(50)).await;
+        tx.send(()).unwrap();
+    });
+    assert_eq!(pool.pending_jobs(), 1);
+    pool.join();
+    assert!(rx.try_recv().is_ok());
+    assert!(handle.is_finished());
+}
+
+#[test]
+fn panicking_future() {
+    let pool = ThreadPool::with_size(1).expect("Expected Ok value");
+    let handle = pool.spawn_future(async { panic!("Expected panic") });
+    let Err(PoolError::JobPanicked(panic)) = job_pool::block_on(handle) else { panic!("Expected a JobPanic") };
+    assert_eq!(panic.message(), Some("Expected panic"));
+    pool.join();
+}
+
+#[test]
+fn woken_after_pool_dropped() {
+    let pool = ThreadPool::with_size(1).expect("Expected Ok value");
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut handle = pool.spawn_future(async move {
+        tx.send(()).unwrap();
+        Sleep::new(Duration::from_millis(50)).await;
+        1
+    });
+    rx.recv().unwrap();
+    drop(pool);
+    assert!(matches!(handle.join_timeout(Duration::from_secs(5)), Err(PoolError::ShutDown)));
+}
+
+#[test]
+fn wake_with_bounded_queue() {
+    let conf = PoolConfig::builder().n_workers(1).incoming_buf_size(1).build();
+    let pool = ThreadPool::new(conf).expect("Expected Ok value");
+    let (polling_tx, polling) = std::sync::mpsc::channel();
+    let (full_tx, full) = std::sync::mpsc::channel();
+    let mut handle = pool.spawn_future(async move {
+        polling_tx.send(()).unwrap();
+        full.recv().unwrap();
+        /* Re-sent by the worker while the queue is full */
+        Yield(1).await;
+        1
+    });
+    polling.recv().unwrap();
+    pool.execute(|| {});
+    full_tx.send(()).unwrap();
+    assert_eq!(handle.join_timeout(Duration::from_secs(5)).expect("Expected Ok value"), 1);
+    assert!(pool.join_timeout(Duration::from_secs(5)).is_ok());
+}
+
+/// Future that wakes itself and returns Pending the given number of times
+struct Yield(usize);
+
+impl Future for Yield {
+    type Output = ();
+
+    fn poll(mut self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 == 0 {
+            return Poll::Ready(())
+        }
+        self.0 -= 1;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+/// Future that completes after a delay, woken from another thread
+struct Sleep(Option<Duration>);
+
+impl Sleep {
+    fn new(d: Duration) -> Self {
+        Self(Some(d))
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        match self.0.take() {
+            Some(d) => {
+                let waker = cx.waker().clone();
+                thread::spawn(move || {
+                    thread::sleep(d);
+                    waker.wake();
+                });
+                Poll::Pending
+            }
+            None => Poll::Ready(()),
+        }
+    }
+}
+
+#[test]
+fn execute_async_waits_for_capacity() {
+    let conf = PoolConfig::builder().n_workers(1).max_jobs(1).build();
+    let pool = ThreadPool::new(conf).expect("Expected Ok value");
+    let (tx, rx) = std::sync::mpsc::channel::<()>();
+    pool.execute(move || rx.recv().unwrap());
+
+    let mut fut = pin!(pool.execute_async(|| {}));
+    let mut cx = Context::from_waker(Waker::noop());
+    assert!(fut.as_mut().poll(&mut cx).is_pending());
+
+    let mut join = pin!(pool.join_async());
+    assert!(join.as_mut().poll(&mut cx).is_pending());
+
+    tx.send(()).unwrap();
+    block_on(fut);
+    block_on(join);
+    assert_eq!(pool.pending_jobs(), 0);
+}
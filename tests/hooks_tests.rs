@@ -0,0 +1,89 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::cell::Cell;
+use std::time::Duration;
+
+use job_pool::{PoolConfig, ThreadPool};
+
+thread_local! {
+    static STARTED: Cell<bool> = const { Cell::new(false) };
+}
+
+#[test]
+fn thread_hooks() {
+    let (started_tx, started) = mpsc::channel();
+    let stopped = Arc::new(AtomicUsize::new(0));
+    let config = {
+        let stopped = Arc::clone(&stopped);
+        PoolConfig::builder()
+                   .n_workers(3_u16)
+                   .on_thread_start(move || {
+                       STARTED.set(true);
+                       started_tx.send(()).unwrap();
+                   })
+                   .on_thread_stop(move || { stopped.fetch_add(1, Ordering::SeqCst); })
+                   .build()
+    };
+    let pool = ThreadPool::new(config).expect("Expected Ok value");
+    let handle = pool.spawn(|| STARTED.get());
+    assert!(handle.join().unwrap());
+    /* The workers signal they started before calling the hook */
+    for _ in 0..3 {
+        started.recv_timeout(Duration::from_secs(5)).unwrap();
+    }
+    assert!(started.try_recv().is_err());
+
+    drop(pool);
+    assert_eq!(stopped.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn job_hooks() {
+    let before = Arc::new(AtomicUsize::new(0));
+    let after = Arc::new(Mutex::new(Vec::new()));
+    let config = {
+        let before = Arc::clone(&before);
+        let after = Arc::clone(&after);
+        PoolConfig::builder()
+                   .n_workers(1_u16)
+                   .before_job(move || { before.fetch_add(1, Ordering::SeqCst); })
+                   .after_job(move |duration, panicked| after.lock().unwrap().push((duration, panicked)))
+                   .build()
+    };
+    let pool = ThreadPool::new(config).expect("Expected Ok value");
+    pool.execute(|| std::thread::sleep(Duration::from_millis(10)));
+    pool.execute(|| panic!("Oops"));
+    /* Each poll of a future counts as a job */
+    pool.spawn_future(async {});
+    pool.join();
+
+    assert_eq!(before.load(Ordering::SeqCst), 3);
+    let after = after.lock().unwrap();
+    assert_eq!(after.len(), 3);
+    assert!(after[0].0 >= Duration::from_millis(10));
+    assert!(!after[0].1);
+    /* The future may be polled before or after the panicking job */
+    assert_eq!(after.iter().filter(|(_, panicked)| *panicked).count(), 1);
+}
+
+#[test]
+fn panicking_hooks() {
+    let stopped = Arc::new(AtomicUsize::new(0));
+    let config = {
+        let stopped = Arc::clone(&stopped);
+        PoolConfig::builder()
+                   .n_workers(1_u16)
+                   .on_thread_start(|| panic!("Expected panic"))
+                   .before_job(|| panic!("Expected panic"))
+                   .after_job(|_, _| panic!("Expected panic"))
+                   .on_thread_stop(move || { stopped.fetch_add(1, Ordering::SeqCst); })
+                   .build()
+    };
+    let pool = ThreadPool::new(config).expect("Expected Ok value");
+    pool.execute(|| {});
+    assert!(pool.join_timeout(Duration::from_secs(5)).is_ok());
+    assert_eq!(pool.spawn(|| 1).join().unwrap(), 1);
+
+    drop(pool);
+    assert_eq!(stopped.load(Ordering::SeqCst), 1);
+}
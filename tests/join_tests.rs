@@ -0,0 +1,81 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use job_pool::{PoolConfig, ThreadPool};
+
+fn fib(pool: &ThreadPool, n: u64) -> u64 {
+    if n < 2 {
+        return n;
+    }
+    let (a, b) = pool.join2(|| fib(pool, n - 1), || fib(pool, n - 2));
+    a + b
+}
+
+#[test]
+fn join2() {
+    let pool = ThreadPool::with_size(4).expect("Expected Ok value");
+    let (a, b) = pool.join2(|| 1 + 1, || "two");
+    assert_eq!(a, 2);
+    assert_eq!(b, "two");
+}
+
+#[test]
+fn recursive_join_from_jobs() {
+    let config = PoolConfig::builder()
+                            .n_workers(2_u16)
+                            .max_jobs(2_u16)
+                            .build();
+    let pool = ThreadPool::new(config).expect("Expected Ok value");
+    pool.scope(|scope| {
+        for _ in 0..2 {
+            scope.execute(|| assert_eq!(fib(&pool, 18), 2584));
+        }
+    });
+    pool.join();
+    assert_eq!(pool.pending_jobs(), 0);
+}
+
+#[test]
+fn recursive_join_with_bounded_queue() {
+    let config = PoolConfig::builder()
+                            .n_workers(2_u16)
+                            .incoming_buf_size(1)
+                            .build();
+    let pool = Arc::new(ThreadPool::new(config).expect("Expected Ok value"));
+    let p = Arc::clone(&pool);
+    let mut handle = pool.spawn(move || {
+        let nums: Vec<u64> = (0..100_000).collect();
+        (fib(&p, 18), p.par_iter(&nums).sum::<u64>())
+    });
+    assert_eq!(handle.join_timeout(Duration::from_secs(5)).expect("Expected Ok value"), (2584, 4999950000));
+    assert!(pool.join_timeout(Duration::from_secs(5)).is_ok());
+}
+
+#[test]
+fn scope_join_borrows() {
+    let pool = ThreadPool::with_size(4).expect("Expected Ok value");
+    let mut nums: Vec<u32> = (0..1000).collect();
+    let count = AtomicUsize::new(0);
+    pool.scope(|scope| {
+        let (l, r) = nums.split_at_mut(500);
+        scope.join(|| l.iter_mut().for_each(|n| *n *= 2),
+                   || r.iter_mut().for_each(|n| *n *= 2));
+        count.fetch_add(1, Ordering::Relaxed);
+    });
+    assert!(nums.iter().enumerate().all(|(i, n)| *n == i as u32 * 2));
+    assert_eq!(count.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn join2_propagates_panic() {
+    let pool = ThreadPool::with_size(2).expect("Expected Ok value");
+    let done = AtomicUsize::new(0);
+    let res = panic::catch_unwind(AssertUnwindSafe(|| {
+        pool.join2(|| panic!("Oops"), || done.fetch_add(1, Ordering::Relaxed))
+    }));
+    assert!(res.is_err());
+    /* b still runs before the panic is propagated */
+    assert_eq!(done.load(Ordering::Relaxed), 1);
+}
@@ -0,0 +1,47 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use job_pool::{PoolConfig, ThreadPool};
+
+#[test]
+fn map_ordered_keeps_order() {
+    let pool = ThreadPool::with_size(4).expect("Expected Ok value");
+    let results: Vec<u64> = pool.map_ordered(0..20_u64, 4, |n| {
+        thread::sleep(Duration::from_millis(20 - n));
+        n * 2
+    }).map(Result::unwrap).collect();
+    assert_eq!(results, (0..20).map(|n| n * 2).collect::<Vec<_>>());
+}
+
+#[test]
+fn map_ordered_bounds_in_flight() {
+    let pool = ThreadPool::with_size(8).expect("Expected Ok value");
+    let taken = Arc::new(AtomicUsize::new(0));
+    let inputs = {
+        let taken = Arc::clone(&taken);
+        (0..100).inspect(move |_| { taken.fetch_add(1, Ordering::SeqCst); })
+    };
+    let mut results = pool.map_ordered(inputs, 3, |n| n);
+    assert_eq!(results.next().unwrap().unwrap(), 0);
+    assert_eq!(taken.load(Ordering::SeqCst), 3);
+    assert_eq!(results.count(), 99);
+}
+
+#[test]
+fn map_unordered() {
+    let config = PoolConfig::builder()
+                            .n_workers(2_u16)
+                            .max_jobs(2_u16)
+                            .build();
+    let pool = ThreadPool::new(config).expect("Expected Ok value");
+    let mut results: Vec<u32> = pool.map_unordered(0..50, 8, |n| {
+        if n == 7 {
+            panic!("Oops");
+        }
+        n
+    }).filter_map(Result::ok).collect();
+    results.sort_unstable();
+    assert_eq!(results, (0..50).filter(|n| *n != 7).collect::<Vec<_>>());
+}
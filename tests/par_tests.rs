@@ -0,0 +1,58 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use job_pool::ThreadPool;
+
+#[test]
+fn map_collect_keeps_order() {
+    let pool = ThreadPool::with_size(4).expect("Expected Ok value");
+    let nums: Vec<usize> = (0..10_000).collect();
+    let doubled: Vec<usize> = pool.par_iter(&nums).map(|n| n * 2).collect();
+    assert_eq!(doubled, nums.iter().map(|n| n * 2).collect::<Vec<_>>());
+
+    let odd: Vec<usize> = pool.par_iter(&nums).filter(|n| *n % 2 == 1).copied().collect();
+    assert_eq!(odd.len(), 5000);
+    assert!(odd.windows(2).all(|w| w[0] < w[1]));
+}
+
+#[test]
+fn for_each_reduce_sum() {
+    let pool = ThreadPool::with_size(3).expect("Expected Ok value");
+    let nums: Vec<u64> = (1..=1000).collect();
+
+    let count = AtomicUsize::new(0);
+    pool.par_iter(&nums).for_each(|_| { count.fetch_add(1, Ordering::Relaxed); });
+    assert_eq!(count.load(Ordering::Relaxed), 1000);
+
+    let max = pool.par_iter(&nums).copied().reduce(|| 0, u64::max);
+    assert_eq!(max, 1000);
+
+    let sum: u64 = pool.par_iter(&nums).sum();
+    assert_eq!(sum, 500500);
+
+    let empty: [u64; 0] = [];
+    assert_eq!(pool.par_iter(&empty).copied().reduce(|| 7, |a, b| a + b), 7);
+}
+
+#[test]
+fn par_iter_propagates_panic() {
+    let pool = ThreadPool::with_size(2).expect("Expected Ok value");
+    let nums: Vec<u32> = (0..100).collect();
+    let res = panic::catch_unwind(AssertUnwindSafe(|| {
+        pool.par_iter(&nums).for_each(|n| if *n == 42 { panic!("Oops") });
+    }));
+    assert!(res.is_err());
+}
+
+#[test]
+fn par_iter_inside_job() {
+    let pool = Arc::new(ThreadPool::with_size(1).expect("Expected Ok value"));
+    let p = Arc::clone(&pool);
+    let mut handle = pool.spawn(move || {
+        let nums: Vec<u64> = (1..=1000).collect();
+        p.par_iter(&nums).sum::<u64>()
+    });
+    assert_eq!(handle.join_timeout(Duration::from_secs(5)).expect("Expected Ok value"), 500500);
+}
@@ -1,5 +1,6 @@
-use std::sync::{Arc, Mutex};
-use job_pool::ThreadPool;
+use std::sync::{Arc, Barrier, Mutex};
+use std::time::{Duration, Instant};
+use job_pool::{JobPanic, PanicHandler, PanicPolicy, PoolConfig, PoolError, Priority, ThreadPool};
 
 #[test]
 fn pool_counter() {
@@ -30,3 +31,277 @@ fn pool_counter() {
     pool.join();
     check(0);
 }
+
+#[test]
+fn spawn_returns_value() {
+    let pool = ThreadPool::with_size(4).expect("Expected Ok value");
+    let handles = (0..64_u64).map(|i| pool.spawn(move || i * 2)).collect::<Vec<_>>();
+    let sum: u64 = handles.into_iter().map(|h| h.join().unwrap()).sum();
+    assert_eq!(sum, (0..64).map(|i| i * 2).sum());
+}
+
+#[test]
+fn spawn_panic() {
+    let pool = ThreadPool::with_size(2).expect("Expected Ok value");
+    let handle = pool.spawn(|| -> u8 { panic!("Expected panic") });
+    let Err(PoolError::JobPanicked(panic)) = handle.join() else { panic!("Expected a JobPanic") };
+    assert_eq!(panic.message(), Some("Expected panic"));
+    pool.join();
+}
+
+#[test]
+fn spawn_join_timeout() {
+    let pool = ThreadPool::with_size(2).expect("Expected Ok value");
+    let (tx, rx) = std::sync::mpsc::channel::<()>();
+    let mut handle = pool.spawn(move || rx.recv().unwrap());
+    assert!(handle.try_join().is_none());
+    assert!(matches!(handle.join_timeout(Duration::from_millis(10)), Err(PoolError::TimedOut)));
+    tx.send(()).unwrap();
+    assert!(handle.join_timeout(Duration::from_secs(5)).is_ok());
+    assert!(handle.is_finished());
+}
+
+#[test]
+fn panic_doesnt_kill_workers() {
+    let pool = ThreadPool::with_size(2).expect("Expected Ok value");
+    for _ in 0..8 {
+        pool.execute(|| panic!("Expected panic"));
+    }
+    pool.join();
+    assert_eq!(pool.pending_jobs(), 0);
+    assert_eq!(pool.spawn(|| 1).join().unwrap(), 1);
+}
+
+#[test]
+fn panic_handler() {
+    let panics = Arc::new(Mutex::new(Vec::new()));
+    let p = Arc::clone(&panics);
+    let handler: PanicHandler = Arc::new(move |panic: JobPanic| {
+        p.lock().unwrap().push(panic.message().unwrap().to_string());
+    });
+    let conf = PoolConfig::builder()
+                          .n_workers(2)
+                          .panic_policy(PanicPolicy::Handler(handler))
+                          .build();
+    let pool = ThreadPool::new(conf).expect("Expected Ok value");
+    pool.execute(|| panic!("Expected panic"));
+    pool.join();
+    assert_eq!(*panics.lock().unwrap(), ["Expected panic"]);
+}
+
+#[test]
+fn panicking_handler() {
+    let conf = PoolConfig::builder()
+                          .n_workers(1)
+                          .panic_policy(PanicPolicy::Handler(Arc::new(|panic| panic!("{panic}"))))
+                          .build();
+    let pool = ThreadPool::new(conf).expect("Expected Ok value");
+    pool.execute(|| panic!("Expected panic"));
+    assert!(pool.join_timeout(Duration::from_secs(5)).is_ok());
+    assert_eq!(pool.pending_jobs(), 0);
+    assert_eq!(pool.spawn(|| 1).join().unwrap(), 1);
+}
+
+#[test]
+fn resize() {
+    let pool = ThreadPool::with_size(2).expect("Expected Ok value");
+    let count = Arc::new(Mutex::new(0));
+    let submit = |n: usize| {
+        for _ in 0..n {
+            let count = Arc::clone(&count);
+            pool.execute(move || *count.lock().unwrap() += 1);
+        }
+    };
+
+    submit(100);
+    pool.set_workers(8).unwrap();
+    assert_eq!(pool.n_workers(), 8);
+    submit(100);
+    pool.set_workers(1).unwrap();
+    assert_eq!(pool.n_workers(), 1);
+    submit(100);
+    pool.join();
+    assert_eq!(*count.lock().unwrap(), 300);
+
+    assert!(pool.set_workers(0).is_err());
+}
+
+#[test]
+fn shrink_under_load() {
+    let pool = ThreadPool::with_size(2).expect("Expected Ok value");
+    let (tx, rx) = std::sync::mpsc::channel::<()>();
+    let rx = Arc::new(Mutex::new(rx));
+    for _ in 0..20 {
+        let rx = Arc::clone(&rx);
+        pool.execute_with_priority(Priority::High, move || rx.lock().unwrap().recv().unwrap());
+    }
+    pool.set_workers(1).unwrap();
+
+    /* The retired worker exits after it's current job,
+     * before the queued jobs */
+    tx.send(()).unwrap();
+    tx.send(()).unwrap();
+    let start = Instant::now();
+    while pool.stats().workers.len() > 1 {
+        assert!(start.elapsed() < Duration::from_secs(5), "The worker didn't retire");
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    assert!(pool.pending_jobs() >= 17);
+
+    for _ in 0..18 {
+        tx.send(()).unwrap();
+    }
+    pool.join();
+}
+
+#[test]
+fn elastic() {
+    let conf = PoolConfig::builder()
+                          .n_workers(1)
+                          .min_workers(1)
+                          .max_workers(4)
+                          .keep_alive(Duration::from_millis(50))
+                          .build();
+    let pool = ThreadPool::new(conf).expect("Expected Ok value");
+    assert_eq!(pool.n_workers(), 1);
+
+    /* The jobs only finish once all of them are running at once */
+    let barrier = Arc::new(Barrier::new(4));
+    for _ in 0..4 {
+        let barrier = Arc::clone(&barrier);
+        pool.execute(move || { barrier.wait(); });
+    }
+    assert!(pool.join_timeout(Duration::from_secs(5)).is_ok());
+    assert_eq!(pool.n_workers(), 4);
+
+    let start = Instant::now();
+    while pool.n_workers() > 1 {
+        assert!(start.elapsed() < Duration::from_secs(5), "The idle workers didn't retire");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(pool.n_workers(), 1);
+}
+
+#[test]
+fn nested_jobs_are_stolen() {
+    let pool = Arc::new(ThreadPool::with_size(4).expect("Expected Ok value"));
+    let count = Arc::new(Mutex::new(0));
+    let p = Arc::clone(&pool);
+    let c = Arc::clone(&count);
+    pool.execute(move || {
+        for _ in 0..256 {
+            let c = Arc::clone(&c);
+            p.execute(move || *c.lock().unwrap() += 1);
+        }
+    });
+    pool.join();
+    assert_eq!(*count.lock().unwrap(), 256);
+}
+
+#[test]
+fn bounded_incoming_buf() {
+    let conf = PoolConfig::builder()
+                          .n_workers(2)
+                          .incoming_buf_size(4)
+                          .build();
+    let pool = ThreadPool::new(conf).expect("Expected Ok value");
+    let count = Arc::new(Mutex::new(0));
+    for _ in 0..100 {
+        let count = Arc::clone(&count);
+        pool.execute(move || *count.lock().unwrap() += 1);
+    }
+    pool.join();
+    assert_eq!(*count.lock().unwrap(), 100);
+}
+
+#[test]
+fn zero_incoming_buf_holds_one_job() {
+    let conf = PoolConfig::builder()
+                          .n_workers(1)
+                          .incoming_buf_size(0)
+                          .build();
+    let pool = ThreadPool::new(conf).expect("Expected Ok value");
+    let (started_tx, started_rx) = std::sync::mpsc::channel();
+    let (tx, rx) = std::sync::mpsc::channel::<()>();
+    pool.execute(move || {
+        started_tx.send(()).unwrap();
+        rx.recv().unwrap();
+    });
+    started_rx.recv().unwrap();
+
+    assert!(pool.try_execute_now(|| {}).is_ok());
+    let Err(err) = pool.try_execute_now(|| {}) else {
+        panic!("Expected QueueFull")
+    };
+    assert!(matches!(err.error(), PoolError::QueueFull));
+    tx.send(()).unwrap();
+    pool.join();
+}
+
+#[test]
+fn thread_names() {
+    let config = PoolConfig::builder()
+                            .n_workers(1_u16)
+                            .thread_name_prefix("worker")
+                            .stack_size(4 * 1024 * 1024)
+                            .build();
+    let pool = ThreadPool::new(config).expect("Expected Ok value");
+    let name = pool.spawn(|| std::thread::current().name().map(String::from));
+    assert_eq!(name.join().unwrap().as_deref(), Some("worker-0"));
+
+    let config = PoolConfig::builder()
+                            .n_workers(2_u16)
+                            .thread_name_fn(|i| format!("io{i}"))
+                            .build();
+    let pool = ThreadPool::new(config).expect("Expected Ok value");
+    pool.set_workers(3).unwrap();
+    let mut names = Vec::new();
+    pool.scope(|scope| {
+        let handles: Vec<_> = (0..30).map(|_| scope.spawn(|| {
+            std::thread::sleep(Duration::from_millis(1));
+            std::thread::current().name().unwrap().to_string()
+        })).collect();
+        names = handles.into_iter().map(|h| h.join().unwrap()).collect();
+    });
+    assert!(names.iter().all(|n| ["io0", "io1", "io2"].contains(&n.as_str())));
+}
+
+#[test]
+fn spawn_error() {
+    let config = PoolConfig::builder()
+                            .n_workers(2_u16)
+                            .stack_size(usize::MAX / 2)
+                            .build();
+    assert!(ThreadPool::new(config).is_err());
+}
+
+#[test]
+fn join_timeout() {
+    let pool = ThreadPool::with_size(1).expect("Expected Ok value");
+    pool.execute(|| std::thread::sleep(Duration::from_millis(200)));
+    assert!(matches!(pool.join_timeout(Duration::from_millis(10)), Err(PoolError::TimedOut)));
+    assert!(pool.join_timeout(Duration::from_secs(5)).is_ok());
+
+    fn run(pool: &ThreadPool) -> job_pool::Result<u32> {
+        let n = pool.spawn(|| 1).join()?;
+        pool.spawn(move || if n == 1 { panic!("Oops") } else { n }).join()?;
+        Ok(n)
+    }
+    assert!(matches!(run(&pool), Err(PoolError::JobPanicked(_))));
+}
+
+#[test]
+fn drop_runs_queued_jobs() {
+    let pool = ThreadPool::with_size(2).expect("Expected Ok value");
+    let count = Arc::new(Mutex::new(0));
+    for i in 0..100 {
+        let count = Arc::clone(&count);
+        let priority = if i % 2 == 0 { Priority::Low } else { Priority::High };
+        pool.execute_with_priority(priority, move || {
+            std::thread::sleep(Duration::from_micros(100));
+            *count.lock().unwrap() += 1;
+        });
+    }
+    drop(pool);
+    assert_eq!(*count.lock().unwrap(), 100);
+}
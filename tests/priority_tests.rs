@@ -0,0 +1,66 @@
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use job_pool::{PoolConfig, Priority, ThreadPool};
+
+/// Blocks the only worker of the pool until the returned sender is used
+fn block(pool: &ThreadPool) -> mpsc::Sender<()> {
+    let (tx, rx) = mpsc::channel();
+    pool.execute(move || rx.recv().unwrap());
+    tx
+}
+
+#[test]
+fn high_priority_first() {
+    let pool = ThreadPool::with_size(1).expect("Expected Ok value");
+    let order = Arc::new(Mutex::new(Vec::new()));
+    let unblock = block(&pool);
+
+    for (i, prio) in [Priority::Low, Priority::Normal, Priority::High].into_iter().enumerate() {
+        let order = Arc::clone(&order);
+        pool.execute_with_priority(prio, move || order.lock().unwrap().push(i));
+    }
+    unblock.send(()).unwrap();
+    pool.join();
+    assert_eq!(*order.lock().unwrap(), [2, 1, 0]);
+}
+
+#[test]
+fn aging() {
+    let conf = PoolConfig::builder()
+                          .n_workers(1)
+                          .aging(Duration::from_millis(5))
+                          .build();
+    let pool = ThreadPool::new(conf).expect("Expected Ok value");
+    let order = Arc::new(Mutex::new(Vec::new()));
+    let unblock = block(&pool);
+
+    let o = Arc::clone(&order);
+    pool.execute_with_priority(Priority::Low, move || o.lock().unwrap().push("low"));
+    std::thread::sleep(Duration::from_millis(20));
+    let o = Arc::clone(&order);
+    pool.execute_with_priority(Priority::High, move || o.lock().unwrap().push("high"));
+
+    unblock.send(()).unwrap();
+    pool.join();
+    assert_eq!(*order.lock().unwrap(), ["low", "high"]);
+}
+
+#[test]
+fn scope_priority() {
+    let pool = ThreadPool::with_size(1).expect("Expected Ok value");
+    let order = Mutex::new(Vec::new());
+    let unblock = block(&pool);
+
+    pool.scope(|scope| {
+        scope.set_priority(Priority::Low);
+        scope.execute(|| order.lock().unwrap().push("low"));
+        scope.subscope(|sub| {
+            assert_eq!(sub.priority(), Priority::Low);
+            sub.execute_with_priority(Priority::High, || order.lock().unwrap().push("high"));
+            unblock.send(()).unwrap();
+        });
+    });
+    assert_eq!(*order.lock().unwrap(), ["high", "low"]);
+}
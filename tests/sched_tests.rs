@@ -0,0 +1,48 @@
+#![cfg(target_os = "linux")]
+
+use job_pool::{Affinity, PoolConfig, PoolError, SchedPolicy, ThreadPool, ThreadSetting};
+
+/// Reads the nice value of the current thread
+fn current_nice() -> i64 {
+    let stat = std::fs::read_to_string("/proc/thread-self/stat").unwrap();
+    /* The command name may contain spaces, so skip past it */
+    let fields: Vec<&str> = stat.rsplit_once(')').unwrap().1.split_whitespace().collect();
+    fields[16].parse().unwrap()
+}
+
+#[test]
+fn background_pool() {
+    let config = PoolConfig::builder()
+                            .n_workers(2_u16)
+                            .sched_policy(SchedPolicy::Batch)
+                            .nice(10)
+                            .build();
+    let pool = ThreadPool::new(config).expect("Expected Ok value");
+    let nice = pool.spawn(current_nice).join().unwrap();
+    assert_eq!(nice, 10);
+}
+
+#[test]
+fn setup_error() {
+    let config = PoolConfig::builder()
+                            .n_workers(2_u16)
+                            .affinity(Affinity::Cores(vec![4096]))
+                            .build();
+    match ThreadPool::new(config) {
+        Err(PoolError::ThreadSetup { setting, .. }) => assert_eq!(setting, ThreadSetting::Affinity),
+        _ => panic!("Expected ThreadSetup error"),
+    }
+}
+
+#[test]
+fn invalid_values() {
+    let config = PoolConfig::builder()
+                            .nice(20)
+                            .build();
+    assert!(matches!(config.validate(), Err(PoolError::InvalidNice(20))));
+
+    let config = PoolConfig::builder()
+                            .sched_policy(SchedPolicy::Fifo(0))
+                            .build();
+    assert!(matches!(config.validate(), Err(PoolError::InvalidRealTimePriority(0))));
+}
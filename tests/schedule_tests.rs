@@ -0,0 +1,139 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use job_pool::{MissedTickPolicy, PoolConfig, PoolError, ThreadPool};
+
+#[test]
+fn execute_after() {
+    let pool = ThreadPool::with_size(2).expect("Expected Ok value");
+    let (tx, rx) = mpsc::channel();
+    let start = Instant::now();
+
+    let tx2 = tx.clone();
+    pool.execute_after(Duration::from_millis(60), move || tx2.send(2).unwrap());
+    pool.execute_at(start + Duration::from_millis(30), move || tx.send(1).unwrap());
+
+    assert_eq!(rx.recv().unwrap(), 1);
+    assert_eq!(rx.recv().unwrap(), 2);
+    assert!(start.elapsed() >= Duration::from_millis(60));
+}
+
+#[test]
+fn execute_after_max_delay() {
+    let pool = ThreadPool::with_size(1).expect("Expected Ok value");
+    let (tx, rx) = mpsc::channel::<()>();
+    pool.execute_after(Duration::MAX, move || tx.send(()).unwrap());
+    /* The job is never due, so it's dropped without running */
+    assert!(rx.recv().is_err());
+    assert_eq!(pool.pending_jobs(), 0);
+}
+
+#[test]
+fn execute_every() {
+    let pool = ThreadPool::with_size(2).expect("Expected Ok value");
+    let count = Arc::new(AtomicUsize::new(0));
+    let c = Arc::clone(&count);
+    let handle = pool.execute_every(Duration::from_millis(10), move || {
+        c.fetch_add(1, Ordering::Relaxed);
+    }).expect("Expected Ok value");
+
+    thread::sleep(Duration::from_millis(100));
+    handle.cancel();
+    assert!(handle.is_cancelled());
+    thread::sleep(Duration::from_millis(20));
+    pool.join();
+
+    let n = count.load(Ordering::Relaxed);
+    assert!(n >= 3, "Only {n} ticks");
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(count.load(Ordering::Relaxed), n);
+}
+
+#[test]
+fn skip_missed_ticks() {
+    let pool = ThreadPool::with_size(2).expect("Expected Ok value");
+    let count = Arc::new(AtomicUsize::new(0));
+    let c = Arc::clone(&count);
+    let handle = pool.execute_every_with(Duration::from_millis(5), MissedTickPolicy::Skip, move || {
+        c.fetch_add(1, Ordering::Relaxed);
+        thread::sleep(Duration::from_millis(50));
+    }).expect("Expected Ok value");
+    thread::sleep(Duration::from_millis(120));
+    handle.cancel();
+    pool.join();
+    assert!(count.load(Ordering::Relaxed) <= 3);
+}
+
+#[test]
+fn catch_up_runs_ticks_one_at_a_time() {
+    let pool = ThreadPool::with_size(2).expect("Expected Ok value");
+    let running = Arc::new(AtomicUsize::new(0));
+    let overlaps = Arc::new(AtomicUsize::new(0));
+    let (r, o) = (Arc::clone(&running), Arc::clone(&overlaps));
+    let handle = pool.execute_every_with(Duration::from_millis(2), MissedTickPolicy::CatchUp, move || {
+        if r.fetch_add(1, Ordering::SeqCst) > 0 {
+            o.fetch_add(1, Ordering::SeqCst);
+        }
+        thread::sleep(Duration::from_millis(20));
+        r.fetch_sub(1, Ordering::SeqCst);
+    }).expect("Expected Ok value");
+
+    /* The missed ticks don't take the other worker */
+    thread::sleep(Duration::from_millis(50));
+    let mut other = pool.spawn(|| 1);
+    assert_eq!(other.join_timeout(Duration::from_millis(500)).expect("Expected the job to run"), 1);
+
+    handle.cancel();
+    pool.join();
+    assert_eq!(overlaps.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn due_jobs_dont_wait_for_max_jobs() {
+    let conf = PoolConfig::builder().n_workers(1).max_jobs(1).build();
+    let pool = ThreadPool::new(conf).expect("Expected Ok value");
+    let (tx, rx) = mpsc::channel::<()>();
+    pool.execute(move || rx.recv().unwrap());
+
+    pool.execute_after(Duration::from_millis(10), || {});
+    pool.execute_after(Duration::from_millis(20), || {});
+    let start = Instant::now();
+    while pool.pending_jobs() < 3 {
+        assert!(start.elapsed() < Duration::from_secs(5), "The timer is blocked");
+        thread::sleep(Duration::from_millis(5));
+    }
+    tx.send(()).unwrap();
+    pool.join();
+}
+
+#[test]
+fn set_period() {
+    let pool = ThreadPool::with_size(2).expect("Expected Ok value");
+    let (tx, rx) = mpsc::channel();
+    let handle = pool.execute_every(Duration::from_secs(60), move || {
+        let _ = tx.send(Instant::now());
+    }).expect("Expected Ok value");
+
+    let start = Instant::now();
+    handle.set_period(Duration::from_millis(20)).expect("Expected Ok value");
+    assert_eq!(handle.period(), Duration::from_millis(20));
+    let tick = rx.recv_timeout(Duration::from_secs(5)).expect("Expected a tick with the new period");
+    assert!(tick - start >= Duration::from_millis(20));
+    let next = rx.recv_timeout(Duration::from_secs(5)).expect("Expected a tick with the new period");
+    assert!(next - tick >= Duration::from_millis(10));
+    handle.cancel();
+}
+
+#[test]
+fn zero_period() {
+    let pool = ThreadPool::with_size(1).expect("Expected Ok value");
+    let err = pool.execute_every(Duration::ZERO, || {}).err().expect("Expected an error");
+    assert!(matches!(err, PoolError::InvalidPeriod));
+
+    let handle = pool.execute_every(Duration::from_secs(60), || {}).expect("Expected Ok value");
+    assert!(matches!(handle.set_period(Duration::ZERO), Err(PoolError::InvalidPeriod)));
+    assert_eq!(handle.period(), Duration::from_secs(60));
+    handle.cancel();
+}
@@ -0,0 +1,71 @@
+use std::panic::{self, AssertUnwindSafe};
+
+use job_pool::{PoolError, ThreadPool};
+
+#[test]
+fn scoped_spawn() {
+    let pool = ThreadPool::with_size(4).expect("Expected Ok value");
+    let nums = (0..1000_u64).collect::<Vec<_>>();
+
+    let (left, right) = nums.split_at(nums.len() / 2);
+    let total = pool.scope(|scope| {
+        let l = scope.spawn(|| left.iter().sum::<u64>());
+        let r = scope.spawn(|| right.iter().sum::<u64>());
+        l.join().unwrap() + r.join().unwrap()
+    });
+    assert_eq!(total, nums.iter().sum());
+}
+
+#[test]
+fn scoped_spawn_panic() {
+    let pool = ThreadPool::with_size(2).expect("Expected Ok value");
+    pool.scope(|scope| {
+        let handle = scope.spawn(|| -> u8 { panic!("Expected panic") });
+        assert!(handle.join().is_err());
+    });
+}
+
+#[test]
+fn scope_propagates_panic() {
+    let pool = ThreadPool::with_size(2).expect("Expected Ok value");
+    let res = panic::catch_unwind(AssertUnwindSafe(|| {
+        pool.scope(|scope| {
+            scope.execute(|| panic!("Expected panic"));
+        });
+    }));
+    let payload = res.unwrap_err();
+    assert_eq!(payload.downcast_ref::<&str>(), Some(&"Expected panic"));
+}
+
+#[test]
+fn try_scope_collects_panics() {
+    let pool = ThreadPool::with_size(4).expect("Expected Ok value");
+    let res = pool.try_scope(|scope| {
+        for _ in 0..3 {
+            scope.execute(|| panic!("Expected panic"));
+        }
+        scope.execute(|| {});
+    });
+    let Err(PoolError::ScopePanicked(err)) = res else { panic!("Expected a ScopeError") };
+    assert_eq!(err.panics().len(), 3);
+    assert!(pool.try_scope(|scope| scope.execute(|| {})).is_ok());
+}
+
+#[test]
+fn unjoined_spawn_panics() {
+    let pool = ThreadPool::with_size(2).expect("Expected Ok value");
+    let res = pool.try_scope(|scope| {
+        scope.spawn(|| -> u8 { panic!("Expected panic") });
+        let joined = scope.spawn(|| -> u8 { panic!("Expected panic") });
+        assert!(joined.join().is_err());
+    });
+    let Err(PoolError::ScopePanicked(err)) = res else { panic!("Expected a ScopeError") };
+    assert_eq!(err.panics().len(), 1);
+
+    let res = panic::catch_unwind(AssertUnwindSafe(|| {
+        pool.scope(|scope| {
+            scope.spawn(|| -> u8 { panic!("Expected panic") });
+        });
+    }));
+    assert!(res.is_err());
+}
@@ -0,0 +1,106 @@
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use job_pool::{CancellationToken, Histogram, PanicPolicy, PoolConfig, ThreadPool};
+
+#[test]
+fn job_counts() {
+    let config = PoolConfig::builder()
+                            .n_workers(2_u16)
+                            .panic_policy(PanicPolicy::Handler(Arc::new(|_| {})))
+                            .build();
+    let pool = ThreadPool::new(config).expect("Expected Ok value");
+    for _ in 0..10 {
+        pool.execute(|| thread::sleep(Duration::from_millis(1)));
+    }
+    pool.execute(|| panic!("Oops"));
+    let token = CancellationToken::new();
+    token.cancel();
+    pool.execute_with_token(token, || {});
+    pool.join();
+
+    let stats = pool.stats();
+    assert_eq!(stats.submitted, 12);
+    assert_eq!(stats.completed, 11);
+    assert_eq!(stats.panicked, 1);
+    assert_eq!(stats.cancelled, 1);
+    assert_eq!(stats.queue_depth, 0);
+    assert_eq!(stats.workers.len(), 2);
+    assert_eq!(stats.workers.iter().map(|w| w.jobs).sum::<u64>(), 11);
+    assert!(stats.workers.iter().map(|w| w.busy_time).sum::<Duration>() >= Duration::from_millis(5));
+    assert_eq!(stats.run_time.count(), 11);
+    assert_eq!(stats.queue_wait.count(), 11);
+    assert!(stats.run_time.quantile(1.0).unwrap() >= Duration::from_millis(1));
+}
+
+#[test]
+fn busy_workers_and_queue_depth() {
+    let pool = ThreadPool::with_size(1).expect("Expected Ok value");
+    let (tx, rx) = mpsc::channel::<()>();
+    let (started_tx, started_rx) = mpsc::channel::<()>();
+    pool.execute(move || {
+        started_tx.send(()).unwrap();
+        rx.recv().unwrap();
+    });
+    pool.execute(|| {});
+    started_rx.recv().unwrap();
+
+    let stats = pool.stats();
+    assert_eq!(stats.busy_workers, 1);
+    assert_eq!(stats.queue_depth, 1);
+    tx.send(()).unwrap();
+}
+
+#[test]
+fn queue_depth_counts_only_jobs() {
+    let pool = ThreadPool::with_size(2).expect("Expected Ok value");
+    let (tx, rx) = mpsc::channel::<()>();
+    let rx = Arc::new(Mutex::new(rx));
+    let (started_tx, started_rx) = mpsc::channel::<()>();
+    for _ in 0..2 {
+        let rx = rx.clone();
+        let started_tx = started_tx.clone();
+        pool.execute(move || {
+            started_tx.send(()).unwrap();
+            rx.lock().unwrap().recv().unwrap();
+        });
+    }
+    started_rx.recv().unwrap();
+    started_rx.recv().unwrap();
+    pool.execute(|| {});
+
+    /* Both workers are busy, so the shutdown message stays queued */
+    pool.set_workers(1).expect("Expected Ok value");
+    assert_eq!(pool.stats().queue_depth, 1);
+    tx.send(()).unwrap();
+    tx.send(()).unwrap();
+}
+
+#[test]
+fn histogram_buckets() {
+    assert_eq!(Histogram::bucket_range(0), (Duration::ZERO, Duration::from_nanos(2)));
+    assert_eq!(Histogram::bucket_range(10), (Duration::from_nanos(1024), Duration::from_nanos(2048)));
+}
+
+#[test]
+fn up_to_date_after_join() {
+    let pool = ThreadPool::with_size(2).expect("Expected Ok value");
+    for i in 1..=50 {
+        pool.execute(|| thread::sleep(Duration::from_micros(100)));
+        pool.join();
+        let stats = pool.stats();
+        assert_eq!(stats.busy_workers, 0);
+        assert_eq!(stats.completed, i);
+        assert_eq!(stats.workers.iter().map(|w| w.jobs).sum::<u64>(), i);
+    }
+
+    let handle = pool.spawn_future(async { 1 });
+    pool.join();
+    assert!(handle.is_finished());
+    let stats = pool.stats();
+    assert_eq!(stats.submitted, 51);
+    assert_eq!(stats.completed, 51);
+    assert_eq!(stats.run_time.count(), 51);
+    assert_eq!(stats.workers.iter().map(|w| w.jobs).sum::<u64>(), 51);
+}
@@ -0,0 +1,110 @@
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Barrier};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use job_pool::{PanicPolicy, PoolConfig, PoolError, ThreadPool};
+
+/// Returns a pool with one worker blocked until the barrier is
+/// waited, and no space for more jobs.
+fn full_pool() -> (ThreadPool, Arc<Barrier>) {
+    let config = PoolConfig::builder()
+                            .n_workers(1_u16)
+                            .max_jobs(1_u16)
+                            .build();
+    let pool = ThreadPool::new(config).expect("Expected Ok value");
+    let barrier = Arc::new(Barrier::new(2));
+    let b = Arc::clone(&barrier);
+    pool.execute(move || { b.wait(); });
+    (pool, barrier)
+}
+
+/// Returns a job that sends 5, and the receiver to check if it ran
+fn job() -> (impl FnOnce() + Send + 'static, Receiver<i32>) {
+    let (tx, rx) = mpsc::channel();
+    (move || tx.send(5).unwrap(), rx)
+}
+
+#[test]
+fn try_execute() {
+    let pool = ThreadPool::with_size(2).expect("Expected Ok value");
+    let (tx, rx) = std::sync::mpsc::channel();
+    pool.try_execute(move || tx.send(12).unwrap()).expect("Expected Ok value");
+    assert_eq!(rx.recv().unwrap(), 12);
+}
+
+#[test]
+fn try_execute_now_full() {
+    let (pool, barrier) = full_pool();
+    let (job, rx) = job();
+    let err = pool.try_execute_now(job).unwrap_err();
+    assert!(matches!(err.error(), PoolError::QueueFull));
+    (err.into_job())();
+    assert_eq!(rx.try_recv().unwrap(), 5);
+
+    barrier.wait();
+    pool.join();
+    pool.try_execute_now(|| {}).expect("Expected Ok value");
+}
+
+#[test]
+fn execute_timeout() {
+    let (pool, barrier) = full_pool();
+    let start = Instant::now();
+    let (job, rx) = job();
+    let err = pool.execute_timeout(job, Duration::from_millis(50)).unwrap_err();
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    assert!(matches!(err.error(), PoolError::TimedOut));
+    (err.into_job())();
+    assert_eq!(rx.try_recv().unwrap(), 5);
+    assert_eq!(pool.pending_jobs(), 1);
+
+    thread::scope(|s| {
+        s.spawn(|| {
+            thread::sleep(Duration::from_millis(20));
+            barrier.wait();
+        });
+        pool.execute_timeout(|| {}, Duration::from_secs(10)).expect("Expected Ok value");
+    });
+}
+
+#[test]
+fn retired_pool_respawns() {
+    let config = PoolConfig::builder()
+                            .n_workers(1_u16)
+                            .min_workers(0_u16)
+                            .keep_alive(Duration::from_millis(10))
+                            .build();
+    let pool = ThreadPool::new(config).expect("Expected Ok value");
+    let start = Instant::now();
+    while pool.n_workers() > 0 {
+        assert!(start.elapsed() < Duration::from_secs(5), "The idle worker didn't retire");
+        thread::sleep(Duration::from_millis(5));
+    }
+
+    let (job, rx) = job();
+    pool.try_execute(job).expect("Expected Ok value");
+    assert_eq!(rx.recv_timeout(Duration::from_secs(5)), Ok(5));
+}
+
+#[test]
+fn payload_panics_on_drop() {
+    struct Bomb;
+    impl Drop for Bomb {
+        fn drop(&mut self) {
+            panic!("Expected panic");
+        }
+    }
+
+    let config = PoolConfig::builder()
+                            .n_workers(1_u16)
+                            .panic_policy(PanicPolicy::Log)
+                            .build();
+    let pool = ThreadPool::new(config).expect("Expected Ok value");
+    pool.execute(|| std::panic::panic_any(Bomb));
+    assert!(pool.join_timeout(Duration::from_secs(5)).is_ok());
+
+    let (job, rx) = job();
+    pool.try_execute(job).expect("Expected Ok value");
+    assert_eq!(rx.recv_timeout(Duration::from_secs(5)), Ok(5));
+}